@@ -1,135 +1,402 @@
 // std
 use std::{
-	mem,
+	cell::LazyCell,
+	collections::{BTreeMap, BTreeSet},
+	fmt::{Display, Formatter, Result as FmtResult},
+	fs, mem,
+	path::{Path, PathBuf},
+	str::FromStr,
 	sync::{Arc, Mutex},
 };
 // crates.io
 use cargo_metadata::{
-	CargoOpt, DependencyKind, Metadata, MetadataCommand, Node, NodeDep, Package, PackageId, Resolve,
+	CargoOpt, Dependency, DependencyKind, Metadata, MetadataCommand, Node, NodeDep, Package,
+	PackageId,
 };
-use fxhash::FxHashMap;
-use once_cell::sync::{Lazy, OnceCell};
-use serde::Serialize;
+use fxhash::{FxHashMap, FxHashSet};
+use indexmap::IndexMap;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use toml_edit::{DocumentMut, Item};
 // cargo-featalign
-use crate::{cli::AnalyzerInitiator, prelude::*, shared::FEATURES, util::GetById};
-
-#[allow(clippy::type_complexity)]
-pub static PROBLEMS: Lazy<Arc<Mutex<FxHashMap<PackageId, Vec<ProblemCrate>>>>> =
-	Lazy::new(|| Arc::new(Mutex::new(FxHashMap::default())));
-pub fn append_problems(id: PackageId, problems: Vec<ProblemCrate>) {
-	if !problems.is_empty() {
-		let mut ps = PROBLEMS.lock().unwrap();
-
-		if let Some(pcs) = ps.get_mut(&id) {
-			problems.into_iter().for_each(|pc| {
-				if !pcs.contains(&pc) {
-					pcs.push(pc);
-				}
-			});
-		} else {
-			ps.insert(id, problems);
+use crate::{
+	cli::AnalyzerInitiator,
+	locator::{Location, Locator},
+	prelude::*,
+	shared::Shared,
+	suppression::Suppression,
+};
+
+/// Problems of each crate, keyed by the crate which must be fixed.
+///
+/// The crates are sorted by name and version, and their problems by dependency and feature.
+pub type Problems = IndexMap<PackageId, Vec<ProblemCrate>>;
+
+/// Result of an analysis.
+#[derive(Debug, Default)]
+pub struct Analysis {
+	pub problems: Problems,
+	/// Suppressions which didn't disregard any problem, sorted.
+	pub unused_suppressions: Vec<String>,
+}
+
+// State of a single analysis, which is shared by the threads.
+#[derive(Debug, Default)]
+struct State {
+	problems: Mutex<Problems>,
+	// Inline suppressions of the local packages.
+	suppressions: Mutex<FxHashMap<PackageId, Arc<Vec<Suppression>>>>,
+}
+impl State {
+	// Each crate is analyzed only once.
+	fn append_problems(&self, id: PackageId, problems: Vec<ProblemCrate>) {
+		if !problems.is_empty() {
+			self.problems.lock().unwrap().insert(id, problems);
+		}
+	}
+
+	// Only the local packages' manifests can be annotated.
+	//
+	// The manifest is loaded without holding the lock, the first loaded suppressions are kept if
+	// the threads race.
+	fn suppressions_of(&self, package: &Package) -> Arc<Vec<Suppression>> {
+		if package.source.is_some() {
+			return Default::default();
 		}
+		if let Some(ss) = self.suppressions.lock().unwrap().get(&package.id) {
+			return ss.clone();
+		}
+
+		let ss =
+			Arc::new(Suppression::load(package.manifest_path.as_std_path()).unwrap_or_default());
+
+		self.suppressions.lock().unwrap().entry(package.id.clone()).or_insert(ss).clone()
 	}
+
+	// The suppressions of the crates which aren't analyzed, e.g. out of the depth, are never used.
+	fn into_analysis(self, analyzed: &FxHashMap<PackageId, usize>) -> Analysis {
+		let mut unused_suppressions = self
+			.suppressions
+			.into_inner()
+			.unwrap()
+			.iter()
+			.filter(|(id, _)| analyzed.contains_key(*id))
+			.flat_map(|(_, ss)| ss.iter().filter(|s| !s.is_used()).map(ToString::to_string))
+			.collect::<Vec<_>>();
+
+		unused_suppressions.sort();
+
+		Analysis { problems: self.problems.into_inner().unwrap(), unused_suppressions }
+	}
+}
+
+// Lookups of the `cargo metadata` output, which are built once.
+#[derive(Debug)]
+struct Index {
+	packages: FxHashMap<PackageId, Package>,
+	nodes: FxHashMap<PackageId, Node>,
+	// Positions of each package's dependency declarations, grouped by the dependency's name.
+	declarations: FxHashMap<PackageId, FxHashMap<String, Vec<usize>>>,
+	workspace_members: FxHashSet<PackageId>,
+	workspace_root: PathBuf,
+	// Treat every workspace member as a root if it's a pure workspace.
+	roots: Vec<PackageId>,
+	// Crates which are built for the target, the others are only built for the host.
+	targets: FxHashSet<PackageId>,
 }
+impl Index {
+	fn new(metadata: Metadata) -> Result<Self> {
+		let Metadata { packages, resolve, workspace_members, workspace_root, .. } = metadata;
+		let resolve =
+			resolve.ok_or_else(|| anyhow::anyhow!("failed to resolve the dependency graph"))?;
+		let roots = match resolve.root {
+			Some(r) => vec![r],
+			None => workspace_members.clone(),
+		};
+		let declarations = packages
+			.iter()
+			.map(|p| {
+				let mut ds = FxHashMap::<_, Vec<_>>::default();
+
+				p.dependencies
+					.iter()
+					.enumerate()
+					.for_each(|(i, d)| ds.entry(d.name.clone()).or_default().push(i));
+
+				(p.id.clone(), ds)
+			})
+			.collect();
+
+		let mut index = Self {
+			packages: packages.into_iter().map(|p| (p.id.clone(), p)).collect(),
+			nodes: resolve.nodes.into_iter().map(|n| (n.id.clone(), n)).collect(),
+			declarations,
+			workspace_members: workspace_members.into_iter().collect(),
+			workspace_root: workspace_root.into(),
+			roots,
+			targets: FxHashSet::default(),
+		};
 
-static WORKSPACE_ONLY: OnceCell<bool> = OnceCell::new();
-static IGNORE: OnceCell<Vec<String>> = OnceCell::new();
-static DEFAULT_STD: OnceCell<bool> = OnceCell::new();
-static NON_DEFAULT_STD: OnceCell<Vec<String>> = OnceCell::new();
+		index.targets = index.targets_of();
+
+		Ok(index)
+	}
+
+	// Crates which are reachable from the roots without the dev and the host edges.
+	fn targets_of(&self) -> FxHashSet<PackageId> {
+		let mut ts = self.roots.iter().cloned().collect::<FxHashSet<_>>();
+		let mut stack = self.roots.clone();
+
+		while let Some(id) = stack.pop() {
+			for d in &self.node(&id).deps {
+				if !is_dev(d) && !self.is_host_edge(d) && ts.insert(d.pkg.clone()) {
+					stack.push(d.pkg.clone());
+				}
+			}
+		}
+
+		ts
+	}
+
+	// The build dependencies and the proc-macro crates are built for the host, instead of the
+	// target.
+	fn is_host_edge(&self, node_dep: &NodeDep) -> bool {
+		let mut ks = node_dep
+			.dep_kinds
+			.iter()
+			.filter(|k| !matches!(k.kind, DependencyKind::Development))
+			.peekable();
+
+		(ks.peek().is_some() && ks.all(|k| matches!(k.kind, DependencyKind::Build)))
+			|| self
+				.package(&node_dep.pkg)
+				.targets
+				.iter()
+				.any(|t| t.kind.iter().any(|k| k == "proc-macro"))
+	}
+
+	fn package(&self, id: &PackageId) -> &Package {
+		&self.packages[id]
+	}
+
+	fn node(&self, id: &PackageId) -> &Node {
+		&self.nodes[id]
+	}
+
+	// Declarations of the dependency in the package's `Cargo.toml`, including the dev ones.
+	fn declarations_of<'a>(
+		&'a self,
+		package: &'a Package,
+		name: &str,
+	) -> impl Iterator<Item = &'a Dependency> {
+		self.declarations[&package.id]
+			.get(name)
+			.into_iter()
+			.flatten()
+			.map(|i| &package.dependencies[*i])
+	}
+}
 
 #[derive(Debug, Clone)]
 pub struct Analyzer {
-	// TODO?: replace with `FxHashMap` packages
-	metadata: Arc<Metadata>,
-	// Remove?
-	resolve: Arc<Resolve>,
+	index: Arc<Index>,
+	shared: Arc<Shared>,
+	initiator: Arc<AnalyzerInitiator>,
 }
 impl Analyzer {
-	pub fn initialize(initiator: AnalyzerInitiator) -> Self {
+	/// Load the metadata of the workspace.
+	///
+	/// If no features are specified, they will be discovered and stored in the shared context.
+	pub fn initialize(mut shared: Shared, initiator: AnalyzerInitiator) -> Result<Self> {
 		let manifest_path = util::manifest_path_of(&initiator.manifest_path);
-
-		WORKSPACE_ONLY.set(initiator.workspace_only).unwrap();
-		IGNORE.set(initiator.ignore).unwrap();
-		DEFAULT_STD.set(initiator.default_std).unwrap();
-		NON_DEFAULT_STD.set(initiator.non_default_std).unwrap();
-
-		let mut metadata = MetadataCommand::new()
+		let metadata = MetadataCommand::new()
 			.manifest_path(&*manifest_path)
 			.features(CargoOpt::AllFeatures)
 			.exec()
-			.unwrap_or_else(|_| {
-				panic!(
-					"failed to execute the `cargo metadata` command for the directory `{}`.",
+			.map_err(|e| {
+				anyhow::anyhow!(
+					"failed to execute the `cargo metadata` command for the directory `{}`, {e}",
 					manifest_path.display()
 				)
-			});
-		let resolve = mem::take(&mut metadata.resolve).unwrap();
+			})?;
+		let index = Index::new(metadata)?;
+
+		if shared.features.is_empty() {
+			shared.features =
+				discover_features(&index, &initiator.include_features, &initiator.exclude_features);
+		}
 
-		Self { metadata: Arc::new(metadata), resolve: Arc::new(resolve) }
+		Ok(Self {
+			index: Arc::new(index),
+			shared: Arc::new(shared),
+			initiator: Arc::new(initiator),
+		})
 	}
 
-	pub fn analyze(self, depth: i16) {
-		let r = self.resolve.root.as_ref().expect(
-			"the `[package]` specified in the `Cargo.toml` cannot be found\n\
-			it appears to be a pure workspace which is not supported",
-		);
-		let n = self.resolve.nodes.get_by_id(r).unwrap().to_owned();
-		let p = self.metadata.get_by_id(&n.id).unwrap().to_owned();
+	/// Package of the given id, which is in the dependency graph.
+	pub fn package(&self, id: &PackageId) -> Option<&Package> {
+		self.index.packages.get(id)
+	}
+
+	/// Root directory of the analyzed workspace.
+	pub fn workspace_root(&self) -> &Path {
+		&self.index.workspace_root
+	}
 
-		self.analyze_crate(n, p, depth, String::new());
+	/// Shared context, including the discovered features.
+	pub fn shared(&self) -> &Shared {
+		&self.shared
 	}
 
-	fn analyze_crate(self, node: Node, package: Package, depth: i16, mut dependency_path: String) {
-		if *WORKSPACE_ONLY.get().unwrap() && !self.is_workspace_member(&package.id)
-			|| IGNORE.get().unwrap().contains(&package.name)
-		{
-			return;
+	pub fn analyze(&self, mut depth: i16) -> Analysis {
+		let state = State::default();
+		// Walk the dependency graph level by level, so that each crate is analyzed only once.
+		// The distance from the roots also marks the visited crates.
+		let mut distances = FxHashMap::default();
+		// The analyzed crates which depend on the crate.
+		let mut parents = FxHashMap::<_, Vec<_>>::default();
+		let mut distance = 0;
+		let mut level = Vec::new();
+
+		for r in &self.index.roots {
+			if !self.is_skipped(r) {
+				distances.insert(r.clone(), distance);
+				level.push(r.clone());
+			}
 		}
 
-		dependency_path.push_str(&format!("/{}", package.name.clone()));
+		loop {
+			self.shared.install(|| {
+				level.par_iter().for_each(|id| {
+					self.analyze_features(
+						&state,
+						self.index.node(id),
+						self.index.package(id),
+						!self.index.targets.contains(id),
+					)
+				})
+			});
 
-		self.analyze_features(&node, &package, &dependency_path);
+			if !in_depth(depth) {
+				break;
+			}
+			if depth > 0 {
+				depth -= 1;
+			}
 
-		if in_depth(depth) {
-			let mut ts = Vec::new();
+			distance += 1;
 
-			for d in &node.deps {
-				if is_dev(d) {
-					continue;
-				}
+			let mut next_level = Vec::new();
 
-				let n = self.resolve.get_by_id(&d.pkg).unwrap().to_owned();
-				let p = self.metadata.get_by_id(&d.pkg).unwrap().to_owned();
-				let dependency_path = dependency_path.clone();
-				let analyzer = self.clone();
+			for id in &level {
+				for d in self.index.node(id).deps.iter().filter(|d| !is_dev(d)) {
+					// Nothing to align on the host edges without the `--host-features`.
+					if self.is_skipped(&d.pkg)
+						|| (self.initiator.host_features.is_empty() && self.index.is_host_edge(d))
+					{
+						continue;
+					}
 
-				if depth > 8 {
-					analyzer.analyze_crate(n, p, depth - 1, dependency_path);
-				} else {
-					shared::activate_thread(&mut ts, move || {
-						analyzer.analyze_crate(n, p, depth - 1, dependency_path)
-					});
+					parents.entry(d.pkg.clone()).or_default().push(id.clone());
+
+					if !distances.contains_key(&d.pkg) {
+						distances.insert(d.pkg.clone(), distance);
+						next_level.push(d.pkg.clone());
+					}
 				}
 			}
 
-			shared::deactivate_threads(ts);
+			if next_level.is_empty() {
+				break;
+			}
+
+			level = next_level;
 		}
+
+		let mut analysis = state.into_analysis(&distances);
+
+		// The paths are complete after the traversal, since a crate might be reached from a deeper
+		// level.
+		for (id, pcs) in analysis.problems.iter_mut() {
+			let dependency_paths = self.dependency_paths_of(id, &distances, &parents);
+
+			pcs.iter_mut().for_each(|pc| pc.dependency_paths = dependency_paths.clone());
+		}
+
+		self.sort_problems(&mut analysis.problems);
+
+		analysis
 	}
 
-	fn analyze_features(&self, node: &Node, package: &Package, dependency_path: &str) {
-		let rs = package
-			.dependencies
-			.iter()
-			.filter_map(|d| d.rename.as_ref().map(|rn| (d.name.as_str(), rn.as_str())))
-			.collect::<Vec<_>>();
-		let has_std_feat = package.features.contains_key("std");
-		let non_optional_deps = if *DEFAULT_STD.get().unwrap() && has_std_feat {
-			package.dependencies.iter().filter(|d| !d.optional).collect::<Vec<_>>()
-		} else {
-			Vec::new()
+	// Collect the distinct paths from the roots to the crate, up to the limit.
+	//
+	// The shortest path is always collected, and the paths are sorted by length.
+	fn dependency_paths_of(
+		&self,
+		id: &PackageId,
+		distances: &FxHashMap<PackageId, usize>,
+		parents: &FxHashMap<PackageId, Vec<PackageId>>,
+	) -> Vec<Vec<PackageId>> {
+		fn walk(
+			path: &mut Vec<PackageId>,
+			paths: &mut Vec<Vec<PackageId>>,
+			limit: usize,
+			distances: &FxHashMap<PackageId, usize>,
+			parents: &FxHashMap<PackageId, Vec<PackageId>>,
+		) {
+			let id = path.last().unwrap().to_owned();
+
+			if distances.get(&id) == Some(&0) {
+				paths.push(path.iter().rev().cloned().collect());
+			}
+
+			let mut ps =
+				parents.get(&id).map(|ps| ps.iter().collect::<Vec<_>>()).unwrap_or_default();
+
+			// Following the closest parent leads to the shortest path.
+			ps.sort_by_key(|p| distances.get(*p));
+
+			for p in ps {
+				if paths.len() >= limit {
+					return;
+				}
+				// Avoid the cycles, which are introduced by the build dependencies.
+				if path.contains(p) {
+					continue;
+				}
+
+				path.push(p.to_owned());
+				walk(path, paths, limit, distances, parents);
+				path.pop();
+			}
+		}
+
+		let mut paths = Vec::new();
+
+		walk(
+			&mut vec![id.to_owned()],
+			&mut paths,
+			self.initiator.max_dependency_paths.max(1),
+			distances,
+			parents,
+		);
+
+		paths.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+
+		paths
+	}
+
+	// The host edges, to the build dependencies and the proc-macro crates, are aligned with the
+	// `--host-features`. All the edges of a host crate are host edges.
+	fn analyze_features(&self, state: &State, node: &Node, package: &Package, host: bool) {
+		let check_default_std = self.initiator.default_std && package.features.contains_key("std");
+		let features_of = |fs: &[String]| {
+			package.features.iter().filter(|(f, _)| fs.contains(f)).collect::<Vec<_>>()
 		};
-		let fs = FEATURES.get().unwrap();
-		let fs = package.features.iter().filter(|(f, _)| fs.contains(f)).collect::<Vec<_>>();
+		let target_fs = features_of(&self.shared.features);
+		let host_fs = features_of(&self.initiator.host_features);
+		let ss = state.suppressions_of(package);
+		let locator = LazyCell::new(|| Locator::load(package.manifest_path.as_std_path()));
 		let mut problem_cs = Vec::new();
 
 		for d in &node.deps {
@@ -137,55 +404,97 @@ impl Analyzer {
 				continue;
 			}
 
+			let edge_host = host || self.index.is_host_edge(d);
+			let (fs, check_default_std) = if edge_host {
+				(
+					&host_fs,
+					check_default_std && self.initiator.host_features.iter().any(|f| f == "std"),
+				)
+			} else {
+				(&target_fs, check_default_std)
+			};
 			let p_id = &d.pkg;
-			let p = self.metadata.get_by_id(p_id).unwrap();
+			let p = self.index.package(p_id);
 			let p_name = p.name.as_str();
-			let p_alias = rs.get_by_id(p_name).unwrap_or(p_name);
-			let n = self.resolve.get_by_id(p_id).unwrap();
+			let p_alias = self
+				.index
+				.declarations_of(package, p_name)
+				.find_map(|d| d.rename.as_deref())
+				.unwrap_or(p_name);
+			let n = self.index.node(p_id);
+			// The features of an optional dependency must be enabled with the weak syntax.
+			let optional = self
+				.index
+				.declarations_of(package, p_name)
+				.filter(|d| !matches!(d.kind, DependencyKind::Development))
+				.all(|d| d.optional);
 			let mut missing_fs = Vec::new();
 
-			if !non_optional_deps.is_empty()
-				&& !is_non_default_std(&d.name)
+			if check_default_std
+				&& !self.is_non_default_std(&d.name)
+				&& !self.is_ignored(&package.name, p_name, p_alias, "default")
 				// Package's dependencies have `std` feature enabled.
-				&& non_optional_deps.iter().any(|d| {
-					d.name == p.name
+				&& self.index.declarations_of(package, p_name).any(|d| {
+					!d.optional
 						&& d.uses_default_features
 						&& p.features
 							.get("default")
 							.map(|dfs| dfs.iter().any(|f| f == "std"))
 							.unwrap_or_default()
-				}) {
+				})
+				// Only the suppressions of the existing problems are used.
+				&& !is_suppressed(&ss, p_name, p_alias, "default")
+			{
+				let inherited =
+					is_default_features_inherited(package.manifest_path.as_std_path(), p_alias);
+				// The inherited dependencies are declared in the workspace's `Cargo.toml`, which is
+				// only known for the workspace members.
+				let location = if inherited && self.is_workspace_member(&package.id) {
+					Locator::load(&self.index.workspace_root.join("Cargo.toml"))
+						.workspace_dependency(p_alias)
+				} else {
+					locator.dependency(p_alias)
+				};
+
 				problem_cs.push(ProblemCrate {
 					id: p_id.to_owned(),
 					alias: p_alias.to_owned(),
-					dependency_path: dependency_path.to_owned(),
+					optional,
+					inherited,
+					host: edge_host,
+					dependency_paths: Vec::new(),
+					locations: vec![location],
 					problem: Problem::DefaultFeaturesEnabled,
 				});
 			}
 
-			'out: for (f, required_fs) in &fs {
+			for (f, required_fs) in fs {
 				// If the dependency has the feature specified by the user for analyzing.
-				if n.features.contains(f) {
-					if package.dependencies.iter().any(|d| {
-						d.name == p_name
-							&& d.uses_default_features
-							&& p.features
-								.get("default")
-								.map(|dfs| dfs.iter().any(|f_| f_ == *f))
-								.unwrap_or_default()
-					}) {
-						continue;
-					}
+				if !n.features.contains(f) || self.is_ignored(&package.name, p_name, p_alias, f) {
+					continue;
+				}
 
-					// `assert!("general-a/std".contains("general-a"));`
-					for f in *required_fs {
-						// TODO: handle the full name here
-						// e.g. this could be `general-a/std` or `general-a?/std`
-						if f.contains(p_alias) {
-							continue 'out;
-						}
-					}
+				if self.index.declarations_of(package, p_name).any(|d| {
+					d.uses_default_features
+						&& p.features
+							.get("default")
+							.map(|dfs| dfs.iter().any(|f_| f_ == *f))
+							.unwrap_or_default()
+				}) {
+					continue;
+				}
+
+				if required_fs.iter().any(|f_| {
+					matches!(
+						FeatureValue::parse(f_),
+						FeatureValue::DependencyFeature { dependency, feature, .. }
+							if dependency == p_alias && feature == *f
+					)
+				}) {
+					continue;
+				}
 
+				if !is_suppressed(&ss, p_name, p_alias, f) {
 					missing_fs.push((*f).to_owned());
 				}
 			}
@@ -194,39 +503,411 @@ impl Analyzer {
 				problem_cs.push(ProblemCrate {
 					id: p_id.to_owned(),
 					alias: p_alias.to_owned(),
-					dependency_path: dependency_path.to_owned(),
+					optional,
+					inherited: false,
+					host: edge_host,
+					dependency_paths: Vec::new(),
+					locations: missing_fs.iter().map(|f| locator.feature(f, p_alias)).collect(),
 					problem: Problem::MissingFeatures(missing_fs),
 				});
 			}
+
+			let mut holes = Vec::new();
+
+			for (f, _) in fs {
+				// The holes are filled in the dependency's manifest, which must be in the scope.
+				// The ones of the external crates are reported as unfixable.
+				if p.features.contains_key(*f)
+					|| self.is_skipped(p_id)
+					|| self.is_ignored(&package.name, p_name, p_alias, f)
+				{
+					continue;
+				}
+
+				let entries = self.hole_entries_of(state, p, f, edge_host);
+
+				if !entries.is_empty() && !is_suppressed(&ss, p_name, p_alias, f) {
+					holes.push(FeatureHole { feature: (*f).to_owned(), entries });
+				}
+			}
+
+			if !holes.is_empty() {
+				problem_cs.push(ProblemCrate {
+					id: p_id.to_owned(),
+					alias: p_alias.to_owned(),
+					optional,
+					inherited: false,
+					host: edge_host,
+					dependency_paths: Vec::new(),
+					locations: holes.iter().map(|h| locator.feature(&h.feature, p_alias)).collect(),
+					problem: Problem::FeatureHoles(holes),
+				});
+			}
+		}
+
+		for (alias, (id, optional, rs)) in self.dangling_references_of(node, package, &ss) {
+			problem_cs.push(ProblemCrate {
+				id,
+				alias: alias.to_owned(),
+				optional,
+				inherited: false,
+				host,
+				dependency_paths: Vec::new(),
+				locations: rs
+					.iter()
+					.map(|r| locator.feature_value(&r.feature, &r.value, alias))
+					.collect(),
+				problem: Problem::DanglingReferences(rs),
+			});
+		}
+
+		state.append_problems(node.id.clone(), problem_cs);
+	}
+
+	// Entries which wire the feature, which the package doesn't define, to the package's
+	// dependencies, e.g. `x/f` or `x?/f`.
+	//
+	// Only the direct dependencies are checked, and the ones whose default features enable the
+	// feature are skipped. The host edges are only checked if the package is a host dependency.
+	// The package's own ignore rules and suppressions apply, without marking the suppressions as
+	// used, since the package might not be analyzed.
+	fn hole_entries_of(
+		&self,
+		state: &State,
+		package: &Package,
+		feature: &str,
+		host: bool,
+	) -> Vec<String> {
+		let ss = state.suppressions_of(package);
+		let mut es = self
+			.index
+			.node(&package.id)
+			.deps
+			.iter()
+			.filter(|d| !is_dev(d) && (host || !self.index.is_host_edge(d)))
+			.filter_map(|d| {
+				let p = self.index.package(&d.pkg);
+				let ds = self
+					.index
+					.declarations_of(package, &p.name)
+					.filter(|d| !matches!(d.kind, DependencyKind::Development))
+					.collect::<Vec<_>>();
+
+				if !p.features.contains_key(feature)
+					|| ds.iter().any(|d| {
+						d.uses_default_features
+							&& p.features
+								.get("default")
+								.is_some_and(|dfs| dfs.iter().any(|f| f == feature))
+					}) {
+					return None;
+				}
+
+				let alias = ds.iter().find_map(|d| d.rename.as_deref()).unwrap_or(&p.name);
+
+				if self.is_ignored(&package.name, &p.name, alias, feature)
+					|| ss.iter().any(|s| s.is_match(&p.name, alias, feature))
+				{
+					return None;
+				}
+
+				let weak = ds.iter().all(|d| d.optional);
+
+				Some(
+					FeatureValue::DependencyFeature { dependency: alias, feature, weak }
+						.to_string(),
+				)
+			})
+			.collect::<Vec<_>>();
+
+		es.sort();
+		es.dedup();
+
+		es
+	}
+
+	// Find the `x/f` and `x?/f` entries of every feature, which refer to an unknown feature of a
+	// dependency, grouped by the alias.
+	//
+	// Cargo fails to load the manifest if an entry refers to an unknown dependency, see the
+	// `--prune`. An optional dependency which isn't activated is absent from the resolved graph,
+	// so its features can't be checked.
+	fn dangling_references_of<'a>(
+		&self,
+		node: &Node,
+		package: &'a Package,
+		suppressions: &[Suppression],
+	) -> BTreeMap<&'a str, (PackageId, bool, Vec<DanglingReference>)> {
+		fn alias_of(dependency: &Dependency) -> &str {
+			dependency.rename.as_deref().unwrap_or(&dependency.name)
+		}
+
+		let mut ps = FxHashMap::<&str, (&Package, bool)>::default();
+
+		// A crate might be declared more than once, e.g. once renamed, the edge's name is the
+		// alias in the code.
+		for d in node.deps.iter().filter(|d| !is_dev(d)) {
+			let p = self.index.package(&d.pkg);
+			let ds = self
+				.index
+				.declarations_of(package, &p.name)
+				.filter(|d| !matches!(d.kind, DependencyKind::Development))
+				.collect::<Vec<_>>();
+			let Some(alias) = ds
+				.iter()
+				.map(|d| alias_of(d))
+				.find(|a| ds.len() == 1 || d.name == a.replace('-', "_"))
+			else {
+				continue;
+			};
+			let optional = ds.iter().filter(|d| alias_of(d) == alias).all(|d| d.optional);
+
+			ps.insert(alias, (p, optional));
+		}
+
+		let mut rs = BTreeMap::<_, (_, _, Vec<_>)>::new();
+
+		for (f, vs) in &package.features {
+			for v in vs {
+				let FeatureValue::DependencyFeature {
+					dependency: alias,
+					feature: dependency_f,
+					..
+				} = FeatureValue::parse(v)
+				else {
+					continue;
+				};
+				let Some((p, optional)) = ps.get(alias) else {
+					continue;
+				};
+
+				if p.features.contains_key(dependency_f)
+					|| self.is_ignored(&package.name, &p.name, alias, dependency_f)
+					|| is_suppressed(suppressions, &p.name, alias, dependency_f)
+				{
+					continue;
+				}
+
+				rs.entry(alias)
+					.or_insert_with(|| (p.id.clone(), *optional, Vec::new()))
+					.2
+					.push(DanglingReference { feature: f.to_owned(), value: v.to_owned() });
+			}
 		}
 
-		append_problems(node.id.clone(), problem_cs);
+		rs
+	}
+
+	// Make the output stable, since the problems are appended from many threads.
+	fn sort_problems(&self, problems: &mut Problems) {
+		let key_of = |id: &PackageId| {
+			let p = self.index.package(id);
+
+			(&p.name, &p.version)
+		};
+
+		problems.sort_by(|a, _, b, _| key_of(a).cmp(&key_of(b)));
+		problems.values_mut().for_each(|pcs| {
+			pcs.iter_mut().for_each(|pc| match &mut pc.problem {
+				Problem::DefaultFeaturesEnabled => (),
+				Problem::MissingFeatures(fs) => sort_with_locations(fs, &mut pc.locations),
+				Problem::DanglingReferences(rs) => sort_with_locations(rs, &mut pc.locations),
+				Problem::FeatureHoles(hs) => sort_with_locations(hs, &mut pc.locations),
+			});
+			pcs.sort_by(|a, b| {
+				(&a.alias, key_of(&a.id), &a.problem).cmp(&(&b.alias, key_of(&b.id), &b.problem))
+			});
+		});
+	}
+
+	fn is_skipped(&self, id: &PackageId) -> bool {
+		self.initiator.workspace_only && !self.is_workspace_member(id)
+			|| self.initiator.ignore.contains(&self.index.package(id).name)
 	}
 
 	fn is_workspace_member(&self, id: &PackageId) -> bool {
-		self.metadata.workspace_members.contains(id)
+		self.index.workspace_members.contains(id)
+	}
+
+	fn is_ignored(&self, parent: &str, dependency: &str, alias: &str, feature: &str) -> bool {
+		self.initiator.ignore_rules.iter().any(|r| r.is_match(parent, dependency, alias, feature))
+	}
+
+	fn is_non_default_std(&self, name: &str) -> bool {
+		self.initiator.non_default_std.iter().any(|n| n == name)
 	}
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct ProblemCrate {
+	/// Id of the dependency.
 	pub id: PackageId,
 	pub alias: String,
-	pub dependency_path: String,
+	pub optional: bool,
+	/// Whether the `default-features` is inherited from the `[workspace.dependencies]`.
+	///
+	/// If so, the root `Cargo.toml` is where the setting must be changed.
+	pub inherited: bool,
+	/// Whether the dependency is built for the host, as a build dependency, a proc-macro crate or
+	/// one of their dependencies.
+	pub host: bool,
+	/// Distinct paths from the roots to the crate which must be fixed, capped by the
+	/// `--max-dependency-paths`.
+	pub dependency_paths: Vec<Vec<PackageId>>,
+	/// Locations of the problem, the dependency's declaration for the `default-features` problem,
+	/// the entry of each missing feature, each dangling reference or each feature hole.
+	pub locations: Vec<Location>,
 	pub problem: Problem,
 }
-// TODO?: this would affect the dependency path
-impl PartialEq for ProblemCrate {
-	fn eq(&self, other: &Self) -> bool {
-		self.id == other.id
+impl ProblemCrate {
+	/// Entry which enables the dependency's feature, e.g. `x/f`, or `x?/f` if it's optional.
+	pub fn entry(&self, feature: &str) -> String {
+		FeatureValue::DependencyFeature { dependency: &self.alias, feature, weak: self.optional }
+			.to_string()
 	}
 }
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum Problem {
 	DefaultFeaturesEnabled,
 	MissingFeatures(Vec<String>),
+	DanglingReferences(Vec<DanglingReference>),
+	FeatureHoles(Vec<FeatureHole>),
+}
+
+/// Entry of a feature, which refers to an unknown feature of a dependency.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct DanglingReference {
+	pub feature: String,
+	/// `x/f` or `x?/f`.
+	pub value: String,
+}
+
+/// Feature which the dependency doesn't define, while the dependency's dependencies do.
+///
+/// The parent's feature can't reach them through the dependency.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct FeatureHole {
+	pub feature: String,
+	/// Entries of the feature to create in the dependency, e.g. `x/f` or `x?/f`.
+	pub entries: Vec<String>,
+}
+
+/// Rule to disregard a specific problem, in the form of `[PARENT:]DEPENDENCY[/FEATURE]`.
+///
+/// Each part supports the `*` wildcard, the omitted parts match everything.
+/// The `default-features` problem is matched by the `default` feature.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct IgnoreRule {
+	pub parent: String,
+	pub dependency: String,
+	pub feature: String,
+}
+impl IgnoreRule {
+	/// The dependency is matched by either the package name or the alias.
+	pub fn is_match(&self, parent: &str, dependency: &str, alias: &str, feature: &str) -> bool {
+		util::is_glob_match(&self.parent, parent)
+			&& (util::is_glob_match(&self.dependency, dependency)
+				|| util::is_glob_match(&self.dependency, alias))
+			&& util::is_glob_match(&self.feature, feature)
+	}
+}
+impl FromStr for IgnoreRule {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (parent, d) = s.split_once(':').unwrap_or(("*", s));
+		let (dependency, feature) = d.split_once('/').unwrap_or((d, "*"));
+
+		if [parent, dependency, feature].iter().any(|p| p.is_empty()) {
+			Err(format!("invalid ignore rule `{s}`, expected `[PARENT:]DEPENDENCY[/FEATURE]`"))
+		} else {
+			Ok(Self {
+				parent: parent.into(),
+				dependency: dependency.into(),
+				feature: feature.into(),
+			})
+		}
+	}
+}
+impl TryFrom<String> for IgnoreRule {
+	type Error = String;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		s.parse()
+	}
+}
+
+/// Value of a feature, which is an entry of the `[features]` table.
+#[derive(Debug, PartialEq, Eq)]
+pub enum FeatureValue<'a> {
+	/// `f`, enables another feature of the same package.
+	Feature(&'a str),
+	/// `dep:x`, enables the optional dependency `x`.
+	Dependency(&'a str),
+	/// `x/f` or the weak form `x?/f`, enables the feature `f` of the dependency `x`.
+	DependencyFeature { dependency: &'a str, feature: &'a str, weak: bool },
+}
+impl<'a> FeatureValue<'a> {
+	pub fn parse(s: &'a str) -> Self {
+		if let Some(d) = s.strip_prefix("dep:") {
+			return Self::Dependency(d);
+		}
+
+		match s.split_once('/') {
+			Some((d, f)) => match d.strip_suffix('?') {
+				Some(d) => Self::DependencyFeature { dependency: d, feature: f, weak: true },
+				None => Self::DependencyFeature { dependency: d, feature: f, weak: false },
+			},
+			None => Self::Feature(s),
+		}
+	}
+}
+impl Display for FeatureValue<'_> {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Self::Feature(f_) => write!(f, "{f_}"),
+			Self::Dependency(d) => write!(f, "dep:{d}"),
+			Self::DependencyFeature { dependency, feature, weak: true } =>
+				write!(f, "{dependency}?/{feature}"),
+			Self::DependencyFeature { dependency, feature, weak: false } =>
+				write!(f, "{dependency}/{feature}"),
+		}
+	}
+}
+
+// Discover the features which are defined by the root crate and at least one of its direct
+// dependencies.
+//
+// If `include` is not empty, the features must match one of its patterns.
+// The features which match any pattern of `exclude` are dropped.
+fn discover_features(index: &Index, include: &[String], exclude: &[String]) -> Vec<String> {
+	let mut fs = BTreeSet::new();
+
+	for r in &index.roots {
+		let p = index.package(r);
+		let dps = index
+			.node(r)
+			.deps
+			.iter()
+			.filter(|d| !is_dev(d) && !index.is_host_edge(d))
+			.map(|d| index.package(&d.pkg))
+			.collect::<Vec<_>>();
+
+		p.features
+			.keys()
+			.filter(|f| *f != "default")
+			.filter(|f| include.is_empty() || include.iter().any(|i| util::is_glob_match(i, f)))
+			.filter(|f| !exclude.iter().any(|e| util::is_glob_match(e, f)))
+			.filter(|f| dps.iter().any(|p| p.features.contains_key(*f)))
+			.for_each(|f| {
+				fs.insert(f.to_owned());
+			});
+	}
+
+	fs.into_iter().collect()
 }
 
 // Check if the this package is under the `[dev-dependencies]`.
@@ -234,8 +915,53 @@ fn is_dev(node_dep: &NodeDep) -> bool {
 	node_dep.dep_kinds.iter().any(|k| matches!(k.kind, DependencyKind::Development))
 }
 
-fn is_non_default_std(name: &str) -> bool {
-	NON_DEFAULT_STD.get().unwrap().iter().any(|n| n == name)
+// Check if the dependency is declared with `workspace = true`.
+//
+// Cargo ignores `default-features = false` in the member, so the default features must be
+// disabled in the `[workspace.dependencies]`, even if the member overrides them with
+// `default-features = true`.
+fn is_default_features_inherited(manifest_path: &Path, alias: &str) -> bool {
+	fn is_inherited(table: &Item, alias: &str) -> bool {
+		["dependencies", "build-dependencies", "build_dependencies"].iter().any(|k| {
+			table
+				.get(k)
+				.and_then(|ds| ds.get(alias))
+				.and_then(Item::as_table_like)
+				.is_some_and(|t| t.get("workspace").and_then(Item::as_bool).unwrap_or_default())
+		})
+	}
+
+	let Some(d) =
+		fs::read_to_string(manifest_path).ok().and_then(|s| s.parse::<DocumentMut>().ok())
+	else {
+		return false;
+	};
+
+	is_inherited(d.as_item(), alias)
+		|| d.get("target")
+			.and_then(Item::as_table_like)
+			.is_some_and(|ts| ts.iter().any(|(_, t)| is_inherited(t, alias)))
+}
+
+// Mark all the matched suppressions as used.
+fn is_suppressed(
+	suppressions: &[Suppression],
+	dependency: &str,
+	alias: &str,
+	feature: &str,
+) -> bool {
+	suppressions.iter().filter(|s| s.suppress(dependency, alias, feature)).count() != 0
+}
+
+// Keep the locations aligned with the entries.
+fn sort_with_locations<T>(entries: &mut Vec<T>, locations: &mut Vec<Location>)
+where
+	T: Ord,
+{
+	let mut els = mem::take(entries).into_iter().zip(mem::take(locations)).collect::<Vec<_>>();
+
+	els.sort();
+	(*entries, *locations) = els.into_iter().unzip();
 }
 
 fn in_depth(depth: i16) -> bool {
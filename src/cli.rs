@@ -1,9 +1,12 @@
-pub use clap::Parser;
+pub use clap::{CommandFactory, FromArgMatches, Parser};
 
 // std
 use std::path::PathBuf;
 // crates.io
-use clap::ValueEnum;
+use clap::{ArgAction, ValueEnum};
+use serde::Deserialize;
+// cargo-featalign
+use crate::analyzer::IgnoreRule;
 
 #[derive(Debug, Parser)]
 #[command(
@@ -26,15 +29,16 @@ pub struct Cli {
 	/// Depth of the dependency tree to process.
 	///
 	/// Use `-1` to process the entire tree.
-	///
-	/// !! Running with this flag under a large project, even with 128 threads configured, is
-	/// incredibly challenging.
+	/// Each crate is processed only once, no matter how many paths lead to it.
 	#[arg(long, value_name = "NUM", default_value_t = 0, allow_hyphen_values = true)]
 	pub depth: i16,
 
 	#[command(flatten)]
 	pub resolver_initiator: ResolverInitiator,
 
+	#[command(flatten)]
+	pub reporter_initiator: ReporterInitiator,
+
 	/// Verbose output.
 	#[arg(long)]
 	pub verbose: bool,
@@ -43,7 +47,11 @@ pub struct Cli {
 #[derive(Debug, Parser)]
 pub struct SharedInitiator {
 	/// Features to process.
-	#[arg(long, required = true, value_name = "[NAME]", value_delimiter = ',')]
+	///
+	/// If no features are specified, neither here nor in the configuration, the features which
+	/// are defined by the root crate and at least one of its direct dependencies will be
+	/// discovered and processed.
+	#[arg(long, value_name = "[NAME]", value_delimiter = ',')]
 	pub features: Vec<String>,
 	/// Number of threads to use.
 	///
@@ -65,6 +73,11 @@ pub struct SharedInitiator {
 	#[arg(long, value_name = "SIZE", default_value_t = 4)]
 	pub indent_size: usize,
 }
+impl Default for SharedInitiator {
+	fn default() -> Self {
+		Self::parse_from(["cargo-featalign"])
+	}
+}
 
 #[derive(Debug, Parser)]
 pub struct AnalyzerInitiator {
@@ -73,18 +86,48 @@ pub struct AnalyzerInitiator {
 	/// If `Cargo.toml` is not provided, it will be searched for under the specified path.
 	#[arg(value_name = "PATH", default_value = "./Cargo.toml")]
 	pub manifest_path: PathBuf,
+	/// Only discover the features which match the given patterns.
+	///
+	/// Supports the `*` wildcard, e.g. `runtime-*`.
+	#[arg(long, value_name = "[PATTERN]", value_delimiter = ',')]
+	pub include_features: Vec<String>,
+	/// Do not discover the features which match the given patterns.
+	///
+	/// Supports the `*` wildcard, e.g. `runtime-*`.
+	#[arg(long, value_name = "[PATTERN]", value_delimiter = ',')]
+	pub exclude_features: Vec<String>,
 	/// Determines whether to process only workspace members.
-	#[arg(long)]
+	#[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", default_value_t = false, action = ArgAction::Set)]
 	pub workspace_only: bool,
 	/// Disregard the analysis of the specified crates.
 	#[arg(long, value_delimiter = ',')]
 	pub ignore: Vec<String>,
+	/// Disregard the specific problems.
+	///
+	/// Format: `[PARENT:]DEPENDENCY[/FEATURE]`, each part supports the `*` wildcard and the
+	/// omitted parts match everything.
+	/// Use `default` as the feature to disregard the `default-features` problem.
+	/// e.g. `pallet-x/runtime-benchmarks`, `z:y`.
+	#[arg(long, value_name = "[RULE]", value_delimiter = ',')]
+	pub ignore_rules: Vec<IgnoreRule>,
+	/// Maximum number of the distinct dependency paths to keep for each problem.
+	#[arg(long, value_name = "NUM", default_value_t = 4)]
+	pub max_dependency_paths: usize,
+	/// Features to process on the host dependencies.
+	///
+	/// The build dependencies, the proc-macro crates and their dependencies are built for the
+	/// host, so they're excluded from the alignment of the `--features` and only aligned with
+	/// these features.
+	#[arg(long, value_name = "[NAME]", value_delimiter = ',')]
+	pub host_features: Vec<String>,
 	/// Determines whether to check default features.
 	///
 	/// This option is useful when working in a no-std environment.
 	/// This feature checks if you have set `default-features = false` while also having a `std =
 	/// ["x/std"]` part to control it separately.
-	#[arg(long)]
+	/// The resolver fixes it by setting `default-features = false` and adding `x/std` to the
+	/// `std` feature.
+	#[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", default_value_t = false, action = ArgAction::Set)]
 	pub default_std: bool,
 	// Specify the crates here that do not use the default to control their `std` feature. This
 	// will prevent the check from being applied to them, which is only useful when enabling the
@@ -92,14 +135,48 @@ pub struct AnalyzerInitiator {
 	#[arg(long, value_delimiter = ',')]
 	pub non_default_std: Vec<String>,
 }
+impl Default for AnalyzerInitiator {
+	fn default() -> Self {
+		Self::parse_from(["cargo-featalign"])
+	}
+}
 
-#[derive(Debug, Parser)]
+#[derive(Clone, Debug, Parser)]
 pub struct ResolverInitiator {
 	/// Wether to sort the required features while aligning.
-	#[arg(long)]
+	#[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", default_value_t = false, action = ArgAction::Set)]
 	pub sort: bool,
+	/// Remove the features' entries which refer to the dependencies that no longer exist.
+	///
+	/// Cargo fails to load these manifests, so the workspace members are pruned before the
+	/// analysis, without `cargo metadata`. In the other modes than the overwrite mode, the stale
+	/// entries are reported and the analysis is skipped.
+	#[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", value_name = "BOOL", default_value_t = false, action = ArgAction::Set)]
+	pub prune: bool,
+}
+impl Default for ResolverInitiator {
+	fn default() -> Self {
+		Self::parse_from(["cargo-featalign"])
+	}
 }
-#[derive(Clone, Debug, ValueEnum)]
+#[derive(Debug, Parser)]
+pub struct ReporterInitiator {
+	/// Format of the analysis result.
+	///
+	/// Text: Groups the problems per crate and ends with a summary.
+	/// Json: Prints the problems as a single JSON object.
+	/// Sarif: Prints a SARIF 2.1.0 log, including the fixes.
+	/// Github: Prints the GitHub Actions `::error` workflow commands.
+	#[arg(long, value_enum, verbatim_doc_comment, default_value_t = Format::Text)]
+	pub format: Format,
+}
+impl Default for ReporterInitiator {
+	fn default() -> Self {
+		Self::parse_from(["cargo-featalign"])
+	}
+}
+#[derive(Clone, Debug, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum IndentSymbol {
 	Tab,
 	Whitespace,
@@ -111,3 +188,11 @@ pub enum Mode {
 	DryRun2,
 	Overwrite,
 }
+#[derive(Clone, Debug, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+	Text,
+	Json,
+	Sarif,
+	Github,
+}
@@ -0,0 +1,185 @@
+// std
+use std::{fs, path::Path};
+// crates.io
+use clap::{ArgMatches, parser::ValueSource};
+use serde::Deserialize;
+use toml_edit::DocumentMut;
+// cargo-featalign
+use crate::{
+	analyzer::IgnoreRule,
+	cli::{Cli, Format, IndentSymbol},
+	prelude::*,
+};
+
+/// Name of the standalone configuration file, which lives next to the root `Cargo.toml`.
+pub const CONFIG_FILE: &str = ".featalign.toml";
+
+/// Settings which can be specified in the configuration file.
+///
+/// The priority is (from high to low):
+/// - CLI flags
+/// - `.featalign.toml`
+/// - `[package.metadata.featalign]`
+/// - `[workspace.metadata.featalign]`
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+	pub features: Option<Vec<String>>,
+	pub indent_symbol: Option<IndentSymbol>,
+	pub indent_size: Option<usize>,
+	pub include_features: Option<Vec<String>>,
+	pub exclude_features: Option<Vec<String>>,
+	pub workspace_only: Option<bool>,
+	pub ignore: Option<Vec<String>>,
+	pub ignore_rules: Option<Vec<IgnoreRule>>,
+	pub max_dependency_paths: Option<usize>,
+	pub host_features: Option<Vec<String>>,
+	pub default_std: Option<bool>,
+	pub non_default_std: Option<Vec<String>>,
+	pub depth: Option<i16>,
+	pub sort: Option<bool>,
+	pub prune: Option<bool>,
+	pub format: Option<Format>,
+}
+impl Config {
+	/// Load the configuration of the given `Cargo.toml`, or the one under the given directory.
+	pub fn load(manifest_path: &Path) -> Result<Self> {
+		let manifest_path = util::manifest_path_of(manifest_path);
+		let s = fs::read_to_string(&manifest_path)?;
+		let d = s.parse::<DocumentMut>()?;
+		let from_metadata = |section: &str| -> Result<Self> {
+			let Some(c) =
+				d.get(section).and_then(|s| s.get("metadata")).and_then(|m| m.get("featalign"))
+			else {
+				return Ok(Self::default());
+			};
+			let c = c
+				.clone()
+				.into_table()
+				.map_err(|_| anyhow::anyhow!("`[{section}.metadata.featalign]` must be a table"))?;
+
+			Ok(toml_edit::de::from_document(DocumentMut::from(c))?)
+		};
+		let mut c = from_metadata("package")?.or(from_metadata("workspace")?);
+		let p = manifest_path.with_file_name(CONFIG_FILE);
+
+		if p.is_file() {
+			c = toml_edit::de::from_str::<Self>(&fs::read_to_string(p)?)?.or(c);
+		}
+
+		Ok(c)
+	}
+
+	/// Fill the missing settings with the given configuration.
+	pub fn or(self, other: Self) -> Self {
+		Self {
+			features: self.features.or(other.features),
+			indent_symbol: self.indent_symbol.or(other.indent_symbol),
+			indent_size: self.indent_size.or(other.indent_size),
+			include_features: self.include_features.or(other.include_features),
+			exclude_features: self.exclude_features.or(other.exclude_features),
+			workspace_only: self.workspace_only.or(other.workspace_only),
+			ignore: self.ignore.or(other.ignore),
+			ignore_rules: self.ignore_rules.or(other.ignore_rules),
+			max_dependency_paths: self.max_dependency_paths.or(other.max_dependency_paths),
+			host_features: self.host_features.or(other.host_features),
+			default_std: self.default_std.or(other.default_std),
+			non_default_std: self.non_default_std.or(other.non_default_std),
+			depth: self.depth.or(other.depth),
+			sort: self.sort.or(other.sort),
+			prune: self.prune.or(other.prune),
+			format: self.format.or(other.format),
+		}
+	}
+
+	/// Apply the configuration to the CLI, the flags which are passed explicitly are kept.
+	pub fn apply(self, cli: &mut Cli, matches: &ArgMatches) {
+		let is_unset = |id: &str| {
+			!matches!(
+				matches.value_source(id),
+				Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+			)
+		};
+
+		if let Some(v) = self.features
+			&& is_unset("features")
+		{
+			cli.shared_initiator.features = v;
+		}
+		if let Some(v) = self.indent_symbol
+			&& is_unset("indent_symbol")
+		{
+			cli.shared_initiator.indent_symbol = v;
+		}
+		if let Some(v) = self.indent_size
+			&& is_unset("indent_size")
+		{
+			cli.shared_initiator.indent_size = v;
+		}
+		if let Some(v) = self.include_features
+			&& is_unset("include_features")
+		{
+			cli.analyzer_initiator.include_features = v;
+		}
+		if let Some(v) = self.exclude_features
+			&& is_unset("exclude_features")
+		{
+			cli.analyzer_initiator.exclude_features = v;
+		}
+		if let Some(v) = self.workspace_only
+			&& is_unset("workspace_only")
+		{
+			cli.analyzer_initiator.workspace_only = v;
+		}
+		if let Some(v) = self.ignore
+			&& is_unset("ignore")
+		{
+			cli.analyzer_initiator.ignore = v;
+		}
+		if let Some(v) = self.ignore_rules
+			&& is_unset("ignore_rules")
+		{
+			cli.analyzer_initiator.ignore_rules = v;
+		}
+		if let Some(v) = self.max_dependency_paths
+			&& is_unset("max_dependency_paths")
+		{
+			cli.analyzer_initiator.max_dependency_paths = v;
+		}
+		if let Some(v) = self.host_features
+			&& is_unset("host_features")
+		{
+			cli.analyzer_initiator.host_features = v;
+		}
+		if let Some(v) = self.default_std
+			&& is_unset("default_std")
+		{
+			cli.analyzer_initiator.default_std = v;
+		}
+		if let Some(v) = self.non_default_std
+			&& is_unset("non_default_std")
+		{
+			cli.analyzer_initiator.non_default_std = v;
+		}
+		if let Some(v) = self.depth
+			&& is_unset("depth")
+		{
+			cli.depth = v;
+		}
+		if let Some(v) = self.sort
+			&& is_unset("sort")
+		{
+			cli.resolver_initiator.sort = v;
+		}
+		if let Some(v) = self.prune
+			&& is_unset("prune")
+		{
+			cli.resolver_initiator.prune = v;
+		}
+		if let Some(v) = self.format
+			&& is_unset("format")
+		{
+			cli.reporter_initiator.format = v;
+		}
+	}
+}
@@ -0,0 +1,44 @@
+//! Cargo features alignment tool.
+//!
+//! The initiators default to the defaults of the CLI, and the library prints nothing.
+//!
+//! ```no_run
+//! use cargo_featalign::{analyzer::Analyzer, cli::*, resolver::Resolver, shared::Shared};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let mut shared_initiator = SharedInitiator::default();
+//! let mut analyzer_initiator = AnalyzerInitiator::default();
+//!
+//! shared_initiator.features = vec!["std".into()];
+//! shared_initiator.mode = Mode::DryRun;
+//! analyzer_initiator.workspace_only = true;
+//! analyzer_initiator.default_std = true;
+//!
+//! let analyzer = Analyzer::initialize(Shared::initialize(shared_initiator)?, analyzer_initiator)?;
+//! let analysis = analyzer.analyze(-1);
+//! let resolver = Resolver::initialize(analyzer.shared().to_owned(), Default::default());
+//!
+//! resolver.resolve(analysis.problems)?.edits.iter().for_each(|e| println!("{}", e.diff()));
+//! # Ok(())
+//! # }
+//! ```
+
+mod prelude {
+	pub(crate) use anyhow::Result;
+
+	pub(crate) use crate::util;
+}
+
+pub mod analyzer;
+pub mod cli;
+pub mod config;
+pub mod locator;
+pub mod reporter;
+pub mod resolver;
+pub mod shared;
+
+mod sorter;
+mod suppression;
+mod util;
+
+#[cfg(test)] mod test;
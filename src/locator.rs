@@ -0,0 +1,137 @@
+// std
+use std::{
+	fs,
+	ops::Range,
+	path::{Path, PathBuf},
+};
+// crates.io
+use serde::Serialize;
+use toml_edit::{ImDocument, Item, TableLike};
+
+/// Range of a problem in a manifest.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Location {
+	pub path: PathBuf,
+	pub start: Position,
+	/// Exclusive.
+	pub end: Position,
+}
+
+/// 1-based position, the column counts the characters.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Position {
+	pub line: usize,
+	pub column: usize,
+}
+impl Position {
+	/// Position of the byte offset in the string.
+	pub fn of(s: &str, offset: usize) -> Self {
+		let s = &s[..offset];
+		let line_start = s.rfind('\n').map(|i| i + 1).unwrap_or_default();
+
+		Self { line: s.matches('\n').count() + 1, column: s[line_start..].chars().count() + 1 }
+	}
+}
+
+/// Locate the entries of a manifest with the `toml_edit` spans.
+#[derive(Debug)]
+pub struct Locator {
+	path: PathBuf,
+	document: Option<ImDocument<String>>,
+}
+impl Locator {
+	/// The locations fall back to the start of the file if the manifest can't be parsed.
+	pub fn load(path: &Path) -> Self {
+		let document = fs::read_to_string(path).ok().and_then(|s| ImDocument::parse(s).ok());
+
+		Self { path: path.to_owned(), document }
+	}
+
+	/// Entry of the feature in the `[features]` table.
+	///
+	/// If the feature is absent, fall back to the `[features]` header, then to the dependency's
+	/// declaration.
+	pub fn feature(&self, feature: &str, alias: &str) -> Location {
+		self.locate(|d| {
+			let fs = d.get("features")?;
+
+			fs.as_table_like().and_then(|t| span_of_entry(t, feature)).or_else(|| fs.span())
+		})
+		.unwrap_or_else(|| self.dependency(alias))
+	}
+
+	/// Value in the feature's array, e.g. `"x/f"`.
+	///
+	/// If the value is absent, fall back to the feature's entry.
+	pub fn feature_value(&self, feature: &str, value: &str, alias: &str) -> Location {
+		self.locate(|d| {
+			d.get("features")?
+				.get(feature)?
+				.as_array()?
+				.iter()
+				.find(|v| v.as_str() == Some(value))?
+				.span()
+		})
+		.unwrap_or_else(|| self.feature(feature, alias))
+	}
+
+	/// Declaration of the dependency, under the `[dependencies]`, the `[build-dependencies]` or
+	/// their `[target.*]` variants.
+	pub fn dependency(&self, alias: &str) -> Location {
+		self.locate(|d| {
+			span_of_dependency(d.as_table(), alias).or_else(|| {
+				d.get("target")?
+					.as_table_like()?
+					.iter()
+					.find_map(|(_, t)| t.as_table_like().and_then(|t| span_of_dependency(t, alias)))
+			})
+		})
+		.unwrap_or_else(|| self.start())
+	}
+
+	/// Declaration of the dependency under the `[workspace.dependencies]`.
+	pub fn workspace_dependency(&self, alias: &str) -> Location {
+		self.locate(|d| {
+			span_of_entry(d.get("workspace")?.get("dependencies")?.as_table_like()?, alias)
+		})
+		.unwrap_or_else(|| self.start())
+	}
+
+	fn locate<F>(&self, f: F) -> Option<Location>
+	where
+		F: FnOnce(&ImDocument<String>) -> Option<Range<usize>>,
+	{
+		let d = self.document.as_ref()?;
+		let span = f(d)?;
+		let s = d.raw();
+
+		Some(Location {
+			path: self.path.clone(),
+			start: Position::of(s, span.start),
+			end: Position::of(s, span.end),
+		})
+	}
+
+	fn start(&self) -> Location {
+		let p = Position { line: 1, column: 1 };
+
+		Location { path: self.path.clone(), start: p.clone(), end: p }
+	}
+}
+
+// From the start of the key to the end of the value, or the header of a `[x.y]` table.
+fn span_of_entry(table: &dyn TableLike, key: &str) -> Option<Range<usize>> {
+	let (k, v) = table.get_key_value(key)?;
+
+	match v {
+		Item::Value(v) => Some(k.span()?.start..v.span()?.end),
+		v => v.span(),
+	}
+}
+
+fn span_of_dependency(table: &dyn TableLike, alias: &str) -> Option<Range<usize>> {
+	["dependencies", "build-dependencies", "build_dependencies"]
+		.iter()
+		.find_map(|k| span_of_entry(table.get(k)?.as_table_like()?, alias))
+}
@@ -1,31 +1,18 @@
-mod prelude {
-	pub(crate) use anyhow::Result;
-
-	pub(crate) use crate::{shared, util};
-}
-
-mod cli;
-use cli::{Cli, Mode, Parser};
-
-mod analyzer;
-use analyzer::{Analyzer, PROBLEMS};
-
-mod sorter;
-
-mod resolver;
-use resolver::Resolver;
-
-mod shared;
-use shared::{MODE, Shared};
-
-mod util;
-
-#[cfg(test)] mod test;
-
 // std
 use std::{env, process};
-
-fn main() -> prelude::Result<()> {
+// crates.io
+use anyhow::Result;
+// cargo-featalign
+use cargo_featalign::{
+	analyzer::{Analysis, Analyzer},
+	cli::{Cli, CommandFactory, FromArgMatches, Mode},
+	config::Config,
+	reporter::Reporter,
+	resolver::{Resolution, Resolver},
+	shared::Shared,
+};
+
+fn main() -> Result<()> {
 	color_eyre::install().map_err(|e| anyhow::anyhow!(e))?;
 
 	let mut args = env::args();
@@ -34,26 +21,79 @@ fn main() -> prelude::Result<()> {
 		args.next();
 	}
 
-	let Cli { shared_initiator, analyzer_initiator, depth, resolver_initiator, verbose } =
-		Cli::parse_from(args);
+	let matches = Cli::command().get_matches_from(args);
+	let mut cli = Cli::from_arg_matches(&matches)?;
+
+	Config::load(&cli.analyzer_initiator.manifest_path)?.apply(&mut cli, &matches);
+
+	let Cli {
+		shared_initiator,
+		analyzer_initiator,
+		depth,
+		resolver_initiator,
+		reporter_initiator,
+		verbose,
+	} = cli;
 	let mut exit_code = 0;
+	let shared = Shared::initialize(shared_initiator)?;
+
+	// Cargo fails to load the manifests with the stale entries.
+	if resolver_initiator.prune {
+		let es = Resolver::initialize(shared.clone(), resolver_initiator.clone())
+			.prune(&analyzer_initiator.manifest_path)?;
+
+		if matches!(shared.mode, Mode::Check | Mode::DryRun) {
+			es.iter().for_each(|e| println!("{}", e.diff()));
+		}
+		if !es.is_empty() && shared.mode != Mode::Overwrite {
+			eprintln!(
+				"error: found the stale entries, which must be pruned with `--mode overwrite` before the analysis"
+			);
+
+			process::exit(-1);
+		}
+	}
 
-	Shared::initialize(shared_initiator);
-	Analyzer::initialize(analyzer_initiator).analyze(depth);
+	let discovered = shared.features.is_empty();
+	let analyzer = Analyzer::initialize(shared, analyzer_initiator)?;
+	let Analysis { problems, unused_suppressions } = analyzer.analyze(depth);
+	let shared = analyzer.shared();
 
-	let problems = PROBLEMS.lock().unwrap();
-	let mode = MODE.get().unwrap();
+	if discovered {
+		eprintln!("discovered features: --features {}", shared.features.join(","));
+	}
 
-	if verbose || matches!(mode, Mode::Check) {
-		println!("{}", serde_json::to_string(&*problems).unwrap());
+	for s in unused_suppressions {
+		eprintln!("warning: unused suppression `{s}`");
 	}
-	if !problems.is_empty() && matches!(mode, Mode::Check | Mode::DryRun | Mode::DryRun2) {
+
+	let resolver = Resolver::initialize(shared.to_owned(), resolver_initiator);
+
+	if verbose || matches!(shared.mode, Mode::Check) {
+		println!(
+			"{}",
+			Reporter::initialize(reporter_initiator).report(&analyzer, &resolver, &problems)?
+		);
+	}
+	if !problems.is_empty() && matches!(shared.mode, Mode::Check | Mode::DryRun | Mode::DryRun2) {
 		exit_code = -1;
 	}
 
-	drop(problems);
+	let Resolution { edits, skipped } = resolver.resolve(problems)?;
+
+	for c in skipped {
+		eprintln!("warning: skipped `{c}`, the external crates can't be fixed");
+	}
 
-	Resolver::initialize(resolver_initiator).resolve()?;
+	for e in edits {
+		for u in &e.unfixable {
+			eprintln!("warning: failed to fix `{}`, {u}", e.path.display());
+		}
+
+		if shared.mode == Mode::DryRun && e.is_changed() {
+			println!("{}", e.diff());
+		}
+	}
 
 	process::exit(exit_code);
 }
@@ -0,0 +1,288 @@
+mod github;
+mod sarif;
+
+// std
+use std::{fmt::Write, path::Path};
+// crates.io
+use cargo_metadata::PackageId;
+// cargo-featalign
+use crate::{
+	analyzer::{Analyzer, Problem, ProblemCrate, Problems},
+	cli::{Format, ReporterInitiator},
+	locator::Location,
+	prelude::*,
+	resolver::Resolver,
+};
+
+#[derive(Clone, Debug)]
+pub struct Reporter {
+	format: Format,
+}
+impl Reporter {
+	pub fn initialize(initiator: ReporterInitiator) -> Self {
+		Self { format: initiator.format }
+	}
+
+	/// Render the problems which are found by the analyzer.
+	///
+	/// The resolver provides the fixes of the SARIF report.
+	/// The GitHub annotations and the SARIF results point at the lines which have to change.
+	pub fn report(
+		&self,
+		analyzer: &Analyzer,
+		resolver: &Resolver,
+		problems: &Problems,
+	) -> Result<String> {
+		match self.format {
+			Format::Text => Ok(text(analyzer, problems)),
+			Format::Json => Ok(serde_json::to_string(problems)?),
+			Format::Sarif => sarif::report(analyzer, resolver, problems),
+			Format::Github => github::report(analyzer, problems),
+		}
+	}
+}
+
+// Rule id and description of each `Problem` variant.
+const RULES: [(&str, &str); 4] = [
+	(
+		"default-features-enabled",
+		"The dependency's default features enable `std`, which should be controlled by the `std` feature.",
+	),
+	("missing-features", "The dependency's feature is not enabled by the same feature."),
+	("dangling-references", "The feature refers to an unknown feature of a dependency."),
+	(
+		"feature-holes",
+		"The dependency doesn't define the feature, which its own dependencies define, so the feature can't reach them.",
+	),
+];
+
+// A single problem to annotate, each missing feature, each dangling reference and each feature hole
+// is a finding on its own.
+struct Finding<'a> {
+	id: &'a PackageId,
+	// Narrowed to the single missing feature, dangling reference or feature hole.
+	problem_crate: ProblemCrate,
+	// Index of the `RULES`.
+	rule_index: usize,
+	message: String,
+	location: &'a Location,
+}
+
+fn findings_of(problems: &Problems) -> Vec<Finding<'_>> {
+	let mut fs = Vec::new();
+
+	for (id, pcs) in problems {
+		for pc in pcs {
+			match &pc.problem {
+				Problem::DefaultFeaturesEnabled => fs.push(Finding {
+					id,
+					problem_crate: pc.clone(),
+					rule_index: 0,
+					message: format!(
+						"`{}` is declared with the default features, which enable `std`",
+						pc.alias
+					),
+					location: &pc.locations[0],
+				}),
+				Problem::MissingFeatures(missing_fs) =>
+					for (f, l) in missing_fs.iter().zip(&pc.locations) {
+						fs.push(Finding {
+							id,
+							problem_crate: ProblemCrate {
+								problem: Problem::MissingFeatures(vec![f.to_owned()]),
+								locations: vec![l.to_owned()],
+								..pc.clone()
+							},
+							rule_index: 1,
+							message: format!("`{}` is missing from the `{f}` feature", pc.entry(f)),
+							location: l,
+						});
+					},
+				Problem::DanglingReferences(rs) =>
+					for (r, l) in rs.iter().zip(&pc.locations) {
+						fs.push(Finding {
+							id,
+							problem_crate: ProblemCrate {
+								problem: Problem::DanglingReferences(vec![r.to_owned()]),
+								locations: vec![l.to_owned()],
+								..pc.clone()
+							},
+							rule_index: 2,
+							message: format!(
+								"`{}` of the `{}` feature refers to an unknown feature",
+								r.value, r.feature
+							),
+							location: l,
+						});
+					},
+				Problem::FeatureHoles(hs) =>
+					for (h, l) in hs.iter().zip(&pc.locations) {
+						fs.push(Finding {
+							id,
+							problem_crate: ProblemCrate {
+								problem: Problem::FeatureHoles(vec![h.to_owned()]),
+								locations: vec![l.to_owned()],
+								..pc.clone()
+							},
+							rule_index: 3,
+							message: format!(
+								"`{}` doesn't define the `{}` feature, which can't reach {}",
+								pc.alias,
+								h.feature,
+								code_list_of(&h.entries)
+							),
+							location: l,
+						});
+					},
+			}
+		}
+	}
+
+	fs
+}
+
+// Slash separated path, relative to the workspace root.
+fn relative_path_of(root: &Path, path: &Path) -> Option<String> {
+	let p = path.strip_prefix(root).ok()?;
+
+	Some(p.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"))
+}
+
+// Group the problems per crate, e.g.
+// ```text
+// nested-a 0.0.0 (/x/mock/nested/a/Cargo.toml)
+//   via mock-runtime 0.0.0 > nested-a 0.0.0
+//   nested-b: missing `nested-b/std` (18:1), `nested-b/try-runtime` (21:1)
+//   nested-d: `default-features` is enabled (/x/mock/Cargo.toml:14:1)
+//
+// found 2 problems in 1 crate: 1 default-features-enabled, 1 missing-features, 0 dangling-references,
+// 0 feature-holes
+// ```
+fn text(analyzer: &Analyzer, problems: &Problems) -> String {
+	let mut s = String::new();
+	let (
+		mut default_features_enabled,
+		mut missing_features,
+		mut dangling_references,
+		mut feature_holes,
+	) = (0, 0, 0, 0);
+
+	for (id, pcs) in problems {
+		let p = analyzer.package(id);
+
+		match p {
+			// The external crates are skipped by the resolver.
+			Some(p) => match &p.source {
+				Some(src) => writeln!(s, "{} {} (external, {src})", p.name, p.version),
+				None => writeln!(s, "{} {} ({})", p.name, p.version, p.manifest_path),
+			},
+			None => writeln!(s, "{id}"),
+		}
+		.unwrap();
+
+		// Omit the path if it's the crate's manifest.
+		let position_of = |l: &Location| {
+			if p.is_some_and(|p| p.manifest_path == l.path) {
+				format!("{}:{}", l.start.line, l.start.column)
+			} else {
+				format!("{}:{}:{}", l.path.display(), l.start.line, l.start.column)
+			}
+		};
+
+		if let Some(pc) = pcs.first() {
+			for path in &pc.dependency_paths {
+				let path = path
+					.iter()
+					.map(|id| match analyzer.package(id) {
+						Some(p) => format!("{} {}", p.name, p.version),
+						None => id.to_string(),
+					})
+					.collect::<Vec<_>>();
+
+				writeln!(s, "  via {}", path.join(" > ")).unwrap();
+			}
+		}
+
+		for pc in pcs {
+			match &pc.problem {
+				Problem::DefaultFeaturesEnabled => {
+					default_features_enabled += 1;
+
+					writeln!(
+						s,
+						"  {}: `default-features` is enabled ({})",
+						pc.alias,
+						position_of(&pc.locations[0])
+					)
+					.unwrap();
+				},
+				Problem::MissingFeatures(fs) => {
+					missing_features += 1;
+
+					let fs = fs
+						.iter()
+						.zip(&pc.locations)
+						.map(|(f, l)| format!("`{}` ({})", pc.entry(f), position_of(l)))
+						.collect::<Vec<_>>();
+
+					writeln!(s, "  {}: missing {}", pc.alias, fs.join(", ")).unwrap();
+				},
+				Problem::DanglingReferences(rs) => {
+					dangling_references += 1;
+
+					let rs = rs
+						.iter()
+						.zip(&pc.locations)
+						.map(|(r, l)| {
+							format!("`{}` in `{}` ({})", r.value, r.feature, position_of(l))
+						})
+						.collect::<Vec<_>>();
+
+					writeln!(s, "  {}: unknown feature {}", pc.alias, rs.join(", ")).unwrap();
+				},
+				Problem::FeatureHoles(hs) => {
+					feature_holes += 1;
+
+					let hs = hs
+						.iter()
+						.zip(&pc.locations)
+						.map(|(h, l)| {
+							format!(
+								"`{}` ({}), which can't reach {}",
+								h.feature,
+								position_of(l),
+								code_list_of(&h.entries)
+							)
+						})
+						.collect::<Vec<_>>();
+
+					writeln!(s, "  {}: undefined {}", pc.alias, hs.join("; ")).unwrap();
+				},
+			}
+		}
+
+		s.push('\n');
+	}
+
+	let total = default_features_enabled + missing_features + dangling_references + feature_holes;
+
+	write!(
+		s,
+		"found {total} problem{} in {} crate{}: {default_features_enabled} default-features-enabled, {missing_features} missing-features, {dangling_references} dangling-references, {feature_holes} feature-holes",
+		plural_of(total),
+		problems.len(),
+		plural_of(problems.len()),
+	)
+	.unwrap();
+
+	s
+}
+
+// `` `a`, `b` ``.
+fn code_list_of(entries: &[String]) -> String {
+	entries.iter().map(|e| format!("`{e}`")).collect::<Vec<_>>().join(", ")
+}
+
+fn plural_of(count: usize) -> &'static str {
+	if count == 1 { "" } else { "s" }
+}
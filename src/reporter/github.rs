@@ -0,0 +1,48 @@
+//! [GitHub Actions workflow commands](https://docs.github.com/en/actions/reference/workflow-commands-for-github-actions#setting-an-error-message).
+
+// std
+use std::fmt::Write;
+// cargo-featalign
+use crate::{
+	analyzer::{Analyzer, Problems},
+	prelude::*,
+	reporter::{self, RULES},
+};
+
+// e.g. `::error
+// file=a/Cargo.toml,line=17,col=1,endLine=17,endColumn=13,title=missing-features::..`.
+pub fn report(analyzer: &Analyzer, problems: &Problems) -> Result<String> {
+	let root = analyzer.workspace_root();
+	let mut s = String::new();
+
+	for f in reporter::findings_of(problems) {
+		let l = f.location;
+		let file = reporter::relative_path_of(root, &l.path)
+			.unwrap_or_else(|| l.path.display().to_string());
+
+		writeln!(
+			s,
+			"::error file={},line={},col={},endLine={},endColumn={},title={}::{}",
+			escape_property(&file),
+			l.start.line,
+			l.start.column,
+			l.end.line,
+			l.end.column,
+			escape_property(RULES[f.rule_index].0),
+			escape_data(&f.message)
+		)?;
+	}
+
+	// Drop the last line break.
+	s.pop();
+
+	Ok(s)
+}
+
+fn escape_data(s: &str) -> String {
+	s.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_property(s: &str) -> String {
+	escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
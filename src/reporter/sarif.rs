@@ -0,0 +1,151 @@
+//! [SARIF 2.1.0](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html) report.
+
+// std
+use std::{ops::Range, path::Path};
+// crates.io
+use cargo_metadata::PackageId;
+use imara_diff::{Algorithm, intern::InternedInput, sources};
+use serde_json::{Value, json};
+// cargo-featalign
+use crate::{
+	analyzer::{Analyzer, Problem, ProblemCrate, Problems},
+	locator::Location,
+	prelude::*,
+	reporter::{self, RULES},
+	resolver::{Edit, Resolver},
+};
+
+const SRCROOT: &str = "%SRCROOT%";
+
+pub fn report(analyzer: &Analyzer, resolver: &Resolver, problems: &Problems) -> Result<String> {
+	let root = analyzer.workspace_root();
+	let mut results = Vec::new();
+
+	for f in reporter::findings_of(problems) {
+		let fix_description = match &f.problem_crate.problem {
+			Problem::DefaultFeaturesEnabled =>
+				format!("Disable the default features of `{}`", f.problem_crate.alias),
+			Problem::MissingFeatures(fs) =>
+				format!("Add `{}` to the `{}` feature", f.problem_crate.entry(&fs[0]), fs[0]),
+			Problem::DanglingReferences(rs) =>
+				format!("Remove `{}` from the `{}` feature", rs[0].value, rs[0].feature),
+			Problem::FeatureHoles(hs) => format!(
+				"Create the `{}` feature of `{}` and enable it by the same feature",
+				hs[0].feature, f.problem_crate.alias
+			),
+		};
+		let changes = changes_of(root, resolver.edit(single(f.id, f.problem_crate))?);
+		let mut result = json!({
+			"ruleId": RULES[f.rule_index].0,
+			"ruleIndex": f.rule_index,
+			"level": "warning",
+			"message": { "text": f.message },
+			"locations": [location_of(root, f.location)],
+		});
+
+		// The problems of the external crates can't be fixed.
+		if !changes.is_empty() {
+			result["fixes"] = json!([{
+				"description": { "text": fix_description },
+				"artifactChanges": changes,
+			}]);
+		}
+
+		results.push(result);
+	}
+
+	let sarif = json!({
+		"$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+		"version": "2.1.0",
+		"runs": [{
+			"tool": {
+				"driver": {
+					"name": env!("CARGO_PKG_NAME"),
+					"version": env!("CARGO_PKG_VERSION"),
+					"informationUri": env!("CARGO_PKG_HOMEPAGE"),
+					"rules": RULES
+						.iter()
+						.map(|(id, description)| json!({
+							"id": id,
+							"shortDescription": { "text": description },
+						}))
+						.collect::<Vec<_>>(),
+				},
+			},
+			"originalUriBaseIds": {
+				SRCROOT: { "uri": format!("file://{}/", root.display()) },
+			},
+			"results": results,
+		}],
+	});
+
+	Ok(serde_json::to_string(&sarif)?)
+}
+
+fn single(id: &PackageId, problem_crate: ProblemCrate) -> Problems {
+	Problems::from_iter([(id.to_owned(), vec![problem_crate])])
+}
+
+fn location_of(root: &Path, location: &Location) -> Value {
+	json!({
+		"physicalLocation": {
+			"artifactLocation": artifact_location_of(root, &location.path),
+			"region": {
+				"startLine": location.start.line,
+				"startColumn": location.start.column,
+				"endLine": location.end.line,
+				"endColumn": location.end.column,
+			},
+		},
+	})
+}
+
+// Relative to the workspace root if possible.
+fn artifact_location_of(root: &Path, path: &Path) -> Value {
+	match reporter::relative_path_of(root, path) {
+		Some(p) => json!({ "uri": p, "uriBaseId": SRCROOT }),
+		None => json!({ "uri": format!("file://{}", path.display()) }),
+	}
+}
+
+fn changes_of(root: &Path, edits: Vec<Edit>) -> Vec<Value> {
+	edits
+		.into_iter()
+		.filter(Edit::is_changed)
+		.map(|e| {
+			json!({
+				"artifactLocation": artifact_location_of(root, &e.path),
+				"replacements": replacements_of(&e.original, &e.fixed),
+			})
+		})
+		.collect()
+}
+
+// Each changed hunk replaces the whole lines, the deleted region ends at the start of the next
+// line.
+fn replacements_of(original: &str, fixed: &str) -> Vec<Value> {
+	let input = InternedInput::new(
+		sources::lines_with_terminator(original),
+		sources::lines_with_terminator(fixed),
+	);
+	let mut rs = Vec::new();
+
+	imara_diff::diff(Algorithm::Histogram, &input, |before: Range<u32>, after: Range<u32>| {
+		let text = input.after[after.start as usize..after.end as usize]
+			.iter()
+			.map(|t| input.interner[*t])
+			.collect::<String>();
+
+		rs.push(json!({
+			"deletedRegion": {
+				"startLine": before.start + 1,
+				"startColumn": 1,
+				"endLine": before.end + 1,
+				"endColumn": 1,
+			},
+			"insertedContent": { "text": text },
+		}));
+	});
+
+	rs
+}
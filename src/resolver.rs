@@ -2,60 +2,181 @@
 use std::{
 	fs::{self, File},
 	io::{BufWriter, Write},
-	mem,
+	path::{Path, PathBuf},
+	sync::Arc,
 };
 // crates.io
 use cargo_metadata::PackageId;
-use fxhash::FxHashMap;
-use once_cell::sync::{Lazy, OnceCell};
-use regex::Regex;
-use toml_edit::{DocumentMut, Value, visit_mut::VisitMut};
+use fxhash::{FxHashMap, FxHashSet};
+use rayon::prelude::*;
+use toml_edit::{
+	Array, DocumentMut, InlineTable, Item, RawString, TableLike, Value, visit_mut::VisitMut,
+};
 // cargo-featalign
 use crate::{
-	analyzer::{PROBLEMS, Problem, ProblemCrate},
+	analyzer::{FeatureHole, FeatureValue, Problem, ProblemCrate, Problems},
 	cli::{Mode, ResolverInitiator},
 	prelude::*,
-	shared::{FEATURES, INDENTATION, MODE},
+	shared::Shared,
 	sorter::SortVisitor,
 };
 
-static PATH_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"path\+file://(/.+?)#").unwrap());
-
-static SORT: OnceCell<bool> = OnceCell::new();
-
 #[derive(Clone, Debug)]
-pub struct Resolver;
+pub struct Resolver {
+	shared: Arc<Shared>,
+	initiator: Arc<ResolverInitiator>,
+}
 impl Resolver {
-	pub fn initialize(initiator: ResolverInitiator) -> Self {
-		SORT.set(initiator.sort).unwrap();
-
-		Self
+	pub fn initialize(shared: Shared, initiator: ResolverInitiator) -> Self {
+		Self { shared: Arc::new(shared), initiator: Arc::new(initiator) }
 	}
 
-	pub fn resolve(self) -> Result<()> {
-		if *MODE.get().unwrap() == Mode::Check {
-			return Ok(());
+	/// Fix the problems according to the mode.
+	///
+	/// Nothing is printed, the dry-run mode leaves the diffs of the edits to the caller.
+	pub fn resolve(&self, problems: Problems) -> Result<Resolution> {
+		if self.shared.mode == Mode::Check {
+			return Ok(Resolution::default());
 		}
 
-		let ps = mem::take(&mut *PROBLEMS.lock().unwrap());
-		let mut ts = Vec::new();
+		let skipped =
+			problems.keys().filter(|c| manifest_path_of(c).is_err()).cloned().collect::<Vec<_>>();
+		let edits = self.edit(problems)?;
 
-		ps.into_iter().for_each(|(c, pcs)| {
-			let r = self.clone();
+		edits.iter().try_for_each(|e| self.apply(e))?;
 
-			shared::activate_thread(&mut ts, move || r.resolve_crate(c, pcs));
-		});
-		for r in shared::deactivate_threads(ts) {
-			r?;
+		Ok(Resolution { edits, skipped })
+	}
+
+	/// Prune the stale entries of the features according to the mode.
+	///
+	/// It works without `cargo metadata`, since Cargo fails to load these manifests.
+	/// Returns the edits of the stale entries, Cargo only loads the manifests after they're
+	/// overwritten.
+	pub fn prune(&self, manifest_path: &Path) -> Result<Vec<Edit>> {
+		let es = self.prune_edits(manifest_path)?;
+
+		es.iter().try_for_each(|e| self.apply(e))?;
+
+		Ok(es)
+	}
+
+	/// Compute the edits which remove the entries of the features that refer to the dependencies
+	/// which no longer exist in any dependency table, e.g. `x/f`, `x?/f` and `dep:x`.
+	///
+	/// The root package and the workspace members are pruned, the edits are sorted by the
+	/// manifest path.
+	pub fn prune_edits(&self, manifest_path: &Path) -> Result<Vec<Edit>> {
+		let ps = util::member_manifest_paths_of(&util::manifest_path_of(manifest_path))?;
+
+		self.shared.install(|| {
+			ps.into_par_iter()
+				.filter_map(|p| prune_manifest(p).transpose())
+				.collect::<Result<Vec<_>>>()
+		})
+	}
+
+	/// Compute the edits which fix the problems, without touching the manifests.
+	///
+	/// The edits are sorted by the manifest path.
+	///
+	/// The problems of the external crates, from a registry or a git repository, are skipped.
+	pub fn edit(&self, problems: Problems) -> Result<Vec<Edit>> {
+		// Group the problems by the manifest, since the inherited dependencies must be fixed in
+		// the workspace's `Cargo.toml`, which might be a package's `Cargo.toml` at the same time.
+		// The feature holes are filled in the dependency's `Cargo.toml`.
+		let mut mps = FxHashMap::<PathBuf, ManifestProblems>::default();
+		// Members which fix the inherited dependencies, keyed by the workspace's `Cargo.toml` and
+		// the alias.
+		let mut inheritors = FxHashMap::<(PathBuf, String), FxHashSet<PathBuf>>::default();
+
+		for (c, mut pcs) in problems {
+			let Ok(p) = manifest_path_of(&c) else {
+				continue;
+			};
+			let mut unfixable = Vec::new();
+
+			for pc in &pcs {
+				// The location is the declaration in the workspace's `Cargo.toml`.
+				if pc.inherited
+					&& matches!(pc.problem, Problem::DefaultFeaturesEnabled)
+					&& let Some(l) = pc.locations.first()
+				{
+					mps.entry(l.path.clone())
+						.or_default()
+						.workspace_dependencies
+						.push(pc.alias.clone());
+					inheritors
+						.entry((l.path.clone(), pc.alias.clone()))
+						.or_default()
+						.insert(p.clone());
+				}
+				if let Problem::FeatureHoles(hs) = &pc.problem {
+					match manifest_path_of(&pc.id) {
+						Ok(dp) => {
+							let mp = mps.entry(dp).or_default();
+
+							mp.id.get_or_insert_with(|| pc.id.clone());
+							mp.feature_holes.extend(hs.iter().cloned());
+						},
+						Err(s) => unfixable.push(format!(
+							"failed to fill the feature holes of `{}`, which is from `{s}`",
+							pc.alias
+						)),
+					}
+				}
+			}
+
+			// Otherwise, the entries would refer to the features which don't exist.
+			pcs.retain(|pc| {
+				!matches!(pc.problem, Problem::FeatureHoles(_)) || manifest_path_of(&pc.id).is_ok()
+			});
+
+			let mp = mps.entry(p).or_default();
+
+			mp.id = Some(c);
+			mp.problem_crates = pcs;
+			mp.unfixable.extend(unfixable);
 		}
 
-		Ok(())
+		// The other members which inherit the dependency would lose its default features.
+		for ((w, a), fixed) in inheritors {
+			for m in util::member_manifest_paths_of(&w)? {
+				if !fixed.contains(&m) {
+					mps.entry(m).or_default().inherited_dependencies.push(a.clone());
+				}
+			}
+		}
+
+		let mut es = self.shared.install(|| {
+			mps.into_par_iter().map(|(p, mp)| self.edit_manifest(p, mp)).collect::<Vec<_>>()
+		});
+
+		// Most of the members don't inherit the dependency.
+		es.retain(|e| e.id.is_some() || e.is_changed() || !e.unfixable.is_empty());
+		es.sort_by(|a, b| a.path.cmp(&b.path));
+
+		Ok(es)
 	}
 
-	fn resolve_crate(self, id: PackageId, problem_crates: Vec<ProblemCrate>) -> Result<()> {
-		let p = manifest_path_of(&id.repr);
-		let s = fs::read_to_string(&p)?;
-		let mut d = s.parse::<DocumentMut>()?;
+	// The problems which can't be fixed are collected, instead of failing the others.
+	fn edit_manifest(&self, path: PathBuf, manifest_problems: ManifestProblems) -> Edit {
+		let ManifestProblems {
+			id,
+			problem_crates,
+			workspace_dependencies,
+			inherited_dependencies,
+			feature_holes,
+			mut unfixable,
+		} = manifest_problems;
+		let (s, mut d) = match load_manifest(&path) {
+			Ok(sd) => sd,
+			Err(e) => {
+				unfixable.push(format!("failed to load the manifest, {e}"));
+
+				return Edit { path, id, original: String::new(), fixed: String::new(), unfixable };
+			},
+		};
 		// Introduce initial state to fix:
 		// ```diff
 		// -runtime-benchmarks = []
@@ -66,58 +187,518 @@ impl Resolver {
 		// + "pallet-evm/runtime-benchmarks"]
 		// ```
 		let mut fs_initial_state = FxHashMap::default();
+		let mut push = |r: Result<(), String>| {
+			if let Err(e) = r {
+				unfixable.push(e);
+			}
+		};
 
-		for (i, pc) in problem_crates.iter().enumerate() {
+		for pc in &problem_crates {
 			match &pc.problem {
-				Problem::DefaultFeaturesEnabled => continue,
-				Problem::MissingFeatures(fs) => fs.iter().for_each(|f| {
-					let fs = d["features"].as_table_mut().unwrap();
-					let fs = fs[f].as_array_mut().unwrap();
-
-					if !fs_initial_state.contains_key(f) {
-						fs_initial_state.insert(f.to_owned(), fs.is_empty());
+				Problem::DefaultFeaturesEnabled => {
+					// Inherited dependencies are fixed in the workspace's `Cargo.toml`, the
+					// overridden `default-features = true` is removed.
+					if pc.inherited {
+						edit_dependency(
+							&mut d,
+							&DEPENDENCY_TABLES,
+							&pc.alias,
+							remove_default_features_of,
+						);
+					} else if !edit_dependency(
+						&mut d,
+						&DEPENDENCY_TABLES,
+						&pc.alias,
+						disable_default_features_of,
+					) {
+						push(Err(format!(
+							"failed to disable the default features of `{}`, the declaration isn't found",
+							pc.alias
+						)));
 					}
 
-					fs.push_formatted(Value::from(format!("{}/{f}", pc.alias)).decorated(
-						INDENTATION.get().unwrap(),
-						if *fs_initial_state.get(f).unwrap() && i == problem_crates.len() - 1 {
-							",\n"
-						} else {
-							""
-						},
-					));
+					// Keep the `std` feature working after disabling the default features.
+					push(self.push_feature(&mut d, &mut fs_initial_state, "std", pc));
+				},
+				Problem::MissingFeatures(fs) => fs
+					.iter()
+					.for_each(|f| push(self.push_feature(&mut d, &mut fs_initial_state, f, pc))),
+				Problem::DanglingReferences(rs) =>
+					rs.iter().for_each(|r| remove_feature_value(&mut d, &r.feature, &r.value)),
+				// The dependency's feature is created by its own edit.
+				Problem::FeatureHoles(hs) => hs.iter().for_each(|h| {
+					push(self.push_feature(&mut d, &mut fs_initial_state, &h.feature, pc))
 				}),
 			}
 		}
 
-		if *SORT.get().unwrap() {
-			SortVisitor(FEATURES.get().unwrap().to_owned()).visit_document_mut(&mut d);
+		for h in &feature_holes {
+			h.entries.iter().for_each(|e| {
+				push(self.push_feature_value(
+					&mut d,
+					&mut fs_initial_state,
+					&h.feature,
+					e.to_owned(),
+				))
+			});
+		}
+
+		// Including the development dependencies, which inherit the same declaration.
+		inherited_dependencies.iter().for_each(|a| {
+			edit_dependency(&mut d, &ALL_DEPENDENCY_TABLES, a, enable_default_features_of);
+		});
+
+		match d.get_mut("workspace").and_then(|w| w.get_mut("dependencies")) {
+			Some(ds) => workspace_dependencies.iter().for_each(|a| {
+				if !disable_default_features_of(ds, a) {
+					push(Err(format!(
+						"failed to disable the default features of `{a}`, the workspace dependency isn't found"
+					)));
+				}
+			}),
+			None => workspace_dependencies.iter().for_each(|a| {
+				push(Err(format!(
+					"failed to disable the default features of `{a}`, the `[workspace.dependencies]` isn't found"
+				)))
+			}),
+		}
+
+		// A multi-line array doesn't fit in `features = { .. }`.
+		if let Some(fs) = d.get_mut("features").and_then(Item::as_inline_table_mut) {
+			fs.fmt();
+		} else {
+			fs_initial_state.into_iter().filter(|(_, empty)| *empty).for_each(|(f, _)| {
+				if let Some(fs) = d["features"].get_mut(&f).and_then(Item::as_array_mut)
+					&& !fs.is_empty()
+				{
+					fs.set_trailing_comma(true);
+					fs.set_trailing("\n");
+				}
+			});
+		}
+
+		if self.initiator.sort {
+			SortVisitor {
+				features: self.shared.features.clone(),
+				indentation: self.shared.indentation.clone(),
+			}
+			.visit_document_mut(&mut d);
+		}
+
+		Edit { path, id, original: s, fixed: d.to_string(), unfixable }
+	}
+
+	fn apply(&self, edit: &Edit) -> Result<()> {
+		let Edit { path, fixed, .. } = edit;
+
+		if !edit.is_changed() {
+			return Ok(());
 		}
 
-		match MODE.get().unwrap() {
-			Mode::Check => (),
-			Mode::DryRun => println!("{id}\n{}", util::diff(&s, &d.to_string())),
+		match &self.shared.mode {
+			Mode::Check | Mode::DryRun => (),
 			m => {
-				let p_tmp = tmp_path_of(&p);
+				let p_tmp = tmp_path_of(path);
 				let f_tmp = File::create(&p_tmp)?;
 				let mut w = BufWriter::new(f_tmp);
 
-				w.write_all(d.to_string().as_bytes())?;
+				w.write_all(fixed.as_bytes())?;
 
 				if *m == Mode::Overwrite {
-					fs::rename(p_tmp, p)?;
+					fs::rename(p_tmp, path)?;
 				}
 			},
 		}
 
 		Ok(())
 	}
+
+	fn push_feature(
+		&self,
+		document: &mut DocumentMut,
+		features_initial_state: &mut FxHashMap<String, bool>,
+		feature: &str,
+		problem_crate: &ProblemCrate,
+	) -> Result<(), String> {
+		self.push_feature_value(
+			document,
+			features_initial_state,
+			feature,
+			problem_crate.entry(feature),
+		)
+	}
+
+	// The `[features]` table and the feature are inserted if absent.
+	//
+	// Cargo also accepts the `features.f = [..]` dotted keys and the `features = { .. }` inline
+	// table.
+	fn push_feature_value(
+		&self,
+		document: &mut DocumentMut,
+		features_initial_state: &mut FxHashMap<String, bool>,
+		feature: &str,
+		value: String,
+	) -> Result<(), String> {
+		let fs = document.entry("features").or_insert_with(toml_edit::table);
+		let inline = fs.is_inline_table();
+		let fs = fs.as_table_like_mut().ok_or_else(|| {
+			format!("failed to add `{value}` to the `{feature}` feature, `features` isn't a table")
+		})?;
+		let fs = fs
+			.entry(feature)
+			.or_insert_with(|| toml_edit::value(Array::new()))
+			.as_array_mut()
+			.ok_or_else(|| {
+				format!("failed to add `{value}` to the `{feature}` feature, which isn't an array")
+			})?;
+
+		features_initial_state.entry(feature.to_owned()).or_insert_with(|| fs.is_empty());
+
+		if fs.iter().any(|f| f.as_str() == Some(&value)) {
+			return Ok(());
+		}
+
+		let indentation = match (inline, fs.is_empty()) {
+			(false, _) => &self.shared.indentation,
+			(true, false) => " ",
+			(true, true) => "",
+		};
+
+		fs.push_formatted(Value::from(value).decorated(indentation, ""));
+
+		Ok(())
+	}
+}
+
+/// Result of a resolution.
+#[derive(Debug, Default)]
+pub struct Resolution {
+	/// Edits of the manifests, including the reasons of the problems which can't be fixed.
+	pub edits: Vec<Edit>,
+	/// External crates, from a registry or a git repository, whose problems can't be fixed.
+	pub skipped: Vec<PackageId>,
+}
+
+/// Fix of a manifest.
+#[derive(Debug)]
+pub struct Edit {
+	pub path: PathBuf,
+	/// `None` if there are only inherited dependencies to fix, or if the manifest is pruned.
+	pub id: Option<PackageId>,
+	/// Empty if the manifest can't be loaded.
+	pub original: String,
+	pub fixed: String,
+	/// Reasons of the problems which can't be fixed, the others are fixed anyway.
+	pub unfixable: Vec<String>,
+}
+impl Edit {
+	pub fn is_changed(&self) -> bool {
+		self.original != self.fixed
+	}
+
+	/// Unified diff of the manifest, headed by the crate or the manifest path.
+	pub fn diff(&self) -> String {
+		let d = util::diff(&self.original, &self.fixed);
+
+		match &self.id {
+			Some(id) => format!("{id}\n{d}"),
+			None => format!("{}\n{d}", self.path.display()),
+		}
+	}
+}
+
+#[derive(Debug, Default)]
+struct ManifestProblems {
+	// `None` if there are only inherited dependencies to fix.
+	id: Option<PackageId>,
+	problem_crates: Vec<ProblemCrate>,
+	// Aliases of the `[workspace.dependencies]` whose default features must be disabled.
+	workspace_dependencies: Vec<String>,
+	// Aliases of the inherited dependencies whose default features must stay enabled, since
+	// they're disabled in the `[workspace.dependencies]`.
+	inherited_dependencies: Vec<String>,
+	// Features to create, so that the parents' features reach the dependencies.
+	feature_holes: Vec<FeatureHole>,
+	// Reasons of the problems which can't be fixed.
+	unfixable: Vec<String>,
+}
+
+// Tables of the dependencies which are built with the features.
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "build-dependencies", "build_dependencies"];
+const ALL_DEPENDENCY_TABLES: [&str; 5] = [
+	"dependencies",
+	"dev-dependencies",
+	"dev_dependencies",
+	"build-dependencies",
+	"build_dependencies",
+];
+
+// Edit the dependency under the given tables and their `[target.*]` variants.
+//
+// Returns `false` if the dependency isn't found.
+fn edit_dependency(
+	document: &mut DocumentMut,
+	tables: &[&str],
+	alias: &str,
+	edit: fn(&mut Item, &str) -> bool,
+) -> bool {
+	let edit_table = |table: &mut dyn TableLike| {
+		let mut found = false;
+
+		for k in tables {
+			if let Some(ds) = table.get_mut(k) {
+				found |= edit(ds, alias);
+			}
+		}
+
+		found
+	};
+	let mut found = edit_table(document.as_table_mut());
+
+	if let Some(ts) = document.get_mut("target").and_then(|ts| ts.as_table_like_mut()) {
+		ts.iter_mut().for_each(|(_, t)| {
+			if let Some(t) = t.as_table_like_mut() {
+				found |= edit_table(t);
+			}
+		});
+	}
+
+	found
+}
+
+// Returns `false` if the dependency isn't found.
+fn remove_default_features_of(dependencies: &mut Item, alias: &str) -> bool {
+	let Some(d) = dependencies.get_mut(alias) else {
+		return false;
+	};
+
+	// Avoid `x = { workspace = true}`.
+	if let Some(t) = d.as_inline_table_mut() {
+		for k in ["default-features", "default_features"] {
+			let last = t.iter().last().is_some_and(|(k_, _)| k_ == k);
+
+			if let Some(r) = t.remove(k)
+				&& last && let Some((_, v)) = t.iter_mut().last()
+			{
+				v.decor_mut().set_suffix(r.decor().suffix().cloned().unwrap_or_default());
+			}
+		}
+	} else if let Some(t) = d.as_table_like_mut() {
+		t.remove("default-features");
+		t.remove("default_features");
+	}
+
+	true
+}
+
+// Cargo enables the default features of an inherited dependency, if the member sets
+// `default-features = true`.
+//
+// Returns `false` if the dependency isn't inherited, or its `default-features` is set.
+fn enable_default_features_of(dependencies: &mut Item, alias: &str) -> bool {
+	let Some(d) = dependencies.get_mut(alias) else {
+		return false;
+	};
+
+	if !d.get("workspace").and_then(Item::as_bool).unwrap_or_default()
+		|| d.get("default-features").is_some()
+		|| d.get("default_features").is_some()
+	{
+		return false;
+	}
+
+	// Avoid `x = { workspace = true , default-features = true }`.
+	if let Some(t) = d.as_inline_table_mut()
+		&& let Some((_, v)) = t.iter_mut().last()
+	{
+		v.decor_mut().set_suffix("");
+	}
+
+	let Some(t) = d.as_table_like_mut() else {
+		return false;
+	};
+
+	t.insert("default-features", toml_edit::value(true));
+
+	true
+}
+
+// Returns `false` if the dependency isn't found.
+fn disable_default_features_of(dependencies: &mut Item, alias: &str) -> bool {
+	let Some(d) = dependencies.get_mut(alias) else {
+		return false;
+	};
+
+	// `x = "1.0"`.
+	if let Some(Value::String(v)) = d.as_value() {
+		let mut t = InlineTable::new();
+
+		t.insert("version", v.value().as_str().into());
+		t.insert("default-features", false.into());
+
+		*t.decor_mut() = v.decor().to_owned();
+		*d = Item::Value(Value::InlineTable(t));
+
+		return true;
+	}
+
+	// Avoid `x = { path = "x" , default-features = false }`.
+	if let Some(t) = d.as_inline_table_mut()
+		&& !t.contains_key("default-features")
+		&& !t.contains_key("default_features")
+		&& let Some((_, v)) = t.iter_mut().last()
+	{
+		v.decor_mut().set_suffix("");
+	}
+
+	// `x = { .. }`, `[dependencies.x]` or `x.workspace = true`.
+	let Some(t) = d.as_table_like_mut() else {
+		return false;
+	};
+	let k =
+		if t.contains_key("default_features") { "default_features" } else { "default-features" };
+
+	match t.get_mut(k) {
+		Some(Item::Value(v)) => {
+			let decor = v.decor().to_owned();
+
+			*v = Value::from(false);
+			*v.decor_mut() = decor;
+		},
+		_ => {
+			t.insert(k, toml_edit::value(false));
+		},
+	}
+
+	true
+}
+
+fn load_manifest(path: &Path) -> Result<(String, DocumentMut)> {
+	let s = fs::read_to_string(path)?;
+	let d = s.parse()?;
+
+	Ok((s, d))
 }
 
-fn manifest_path_of(s: &str) -> String {
-	format!("{}/Cargo.toml", &PATH_REGEX.captures(s).unwrap()[1])
+// Remove the entries of the features which refer to the removed dependencies.
+//
+// Returns `None` if there is nothing to prune.
+fn prune_manifest(path: PathBuf) -> Result<Option<Edit>> {
+	let (s, mut d) = load_manifest(&path)?;
+	let Some(fs) = d.get("features").and_then(Item::as_table_like) else {
+		return Ok(None);
+	};
+	let aliases = dependency_aliases_of(&d);
+	// `f` might enable the removed optional dependency `f`, which is an implicit feature.
+	let is_stale = |v: &str| match FeatureValue::parse(v) {
+		FeatureValue::Feature(f) => !fs.contains_key(f) && !aliases.contains(f),
+		FeatureValue::Dependency(d) | FeatureValue::DependencyFeature { dependency: d, .. } =>
+			!aliases.contains(d),
+	};
+	let stale_vs = fs
+		.iter()
+		.flat_map(|(f, vs)| {
+			vs.as_array()
+				.into_iter()
+				.flatten()
+				.filter_map(|v| v.as_str())
+				.filter(|v| is_stale(v))
+				.map(move |v| (f.to_owned(), v.to_owned()))
+		})
+		.collect::<Vec<_>>();
+
+	if stale_vs.is_empty() {
+		return Ok(None);
+	}
+
+	stale_vs.iter().for_each(|(f, v)| remove_feature_value(&mut d, f, v));
+
+	Ok(Some(Edit { path, id: None, original: s, fixed: d.to_string(), unfixable: Vec::new() }))
+}
+
+// Keys of the dependency tables, including the dev ones and the `[target.*]` variants.
+fn dependency_aliases_of(document: &DocumentMut) -> FxHashSet<String> {
+	let mut ts = ALL_DEPENDENCY_TABLES.iter().filter_map(|k| document.get(k)).collect::<Vec<_>>();
+
+	if let Some(targets) = document.get("target").and_then(Item::as_table_like) {
+		targets
+			.iter()
+			.for_each(|(_, t)| ts.extend(ALL_DEPENDENCY_TABLES.iter().filter_map(|k| t.get(k))));
+	}
+
+	ts.into_iter()
+		.filter_map(Item::as_table_like)
+		.flat_map(|t| t.iter().map(|(k, _)| k.to_owned()))
+		.collect()
 }
 
-fn tmp_path_of(p: &str) -> String {
-	format!("{p}.cargo-featalign.swap")
+// Drop the value from the feature's array, e.g. `"x/f"`.
+//
+// The comments and the blank lines above the value are kept, so the groups of the `SortVisitor`
+// stay the same.
+fn remove_feature_value(document: &mut DocumentMut, feature: &str, value: &str) {
+	fn prefix_of(value: &Value) -> &str {
+		value.decor().prefix().and_then(RawString::as_str).unwrap_or_default()
+	}
+
+	let Some(fs) = document
+		.get_mut("features")
+		.and_then(|fs| fs.get_mut(feature))
+		.and_then(Item::as_array_mut)
+	else {
+		return;
+	};
+	let Some(i) = fs.iter().position(|v| v.as_str() == Some(value)) else {
+		return;
+	};
+	let v = fs.remove(i);
+	let prefix = prefix_of(&v);
+
+	if prefix.trim().is_empty() {
+		// Avoid `[ "y/f"]` and keep the blank lines which separate the groups.
+		if let Some(next) = fs.get_mut(i)
+			&& (i == 0 || prefix.matches('\n').count() > prefix_of(next).matches('\n').count())
+		{
+			next.decor_mut().set_prefix(prefix);
+		}
+	} else {
+		// Move the comments, e.g. `# x` or `# "x/f"`, to the next value.
+		let comments = prefix.trim_end();
+
+		match fs.get_mut(i) {
+			Some(next) => {
+				let p = format!("{comments}{}", prefix_of(next));
+
+				next.decor_mut().set_prefix(p);
+			},
+			None => {
+				let t = format!("{comments}{}", fs.trailing().as_str().unwrap_or_default());
+
+				fs.set_trailing(t);
+			},
+		}
+	}
+
+	// Avoid `[\n]`.
+	if fs.is_empty() && fs.trailing().as_str().is_none_or(|t| t.trim().is_empty()) {
+		fs.set_trailing_comma(false);
+		fs.set_trailing("");
+	}
+}
+
+// The manifest of a local crate, or the source of an external crate.
+//
+// e.g. `path+file:///x/a#0.1.0` or `registry+https://github.com/rust-lang/crates.io-index#b@0.1.0`.
+fn manifest_path_of(id: &PackageId) -> Result<PathBuf, &str> {
+	let s = id.repr.split_once('#').map_or(&*id.repr, |(s, _)| s);
+
+	s.strip_prefix("path+file://").map(|p| Path::new(p).join("Cargo.toml")).ok_or(s)
+}
+
+fn tmp_path_of(p: &Path) -> PathBuf {
+	let mut p = p.as_os_str().to_owned();
+
+	p.push(".cargo-featalign.swap");
+
+	p.into()
 }
@@ -1,56 +1,51 @@
 // std
-use std::{
-	sync::atomic::{AtomicU16, Ordering},
-	thread::{self, JoinHandle},
-};
+use std::sync::Arc;
 // crates.io
-use once_cell::sync::{Lazy, OnceCell};
+use rayon::{ThreadPool, ThreadPoolBuilder};
 // cargo-featalign
-use crate::cli::{IndentSymbol, Mode, SharedInitiator};
-
-pub static FEATURES: OnceCell<Vec<String>> = OnceCell::new();
-pub static MODE: OnceCell<Mode> = OnceCell::new();
-pub static INDENTATION: OnceCell<String> = OnceCell::new();
-
-static THREAD: OnceCell<u16> = OnceCell::new();
-static THREAD_ACTIVE: Lazy<AtomicU16> = Lazy::new(|| AtomicU16::new(1));
-// TODO: optimize to `active_threads`
-pub fn activate_thread<F, T>(threads_pool: &mut Vec<JoinHandle<T>>, f: F)
-where
-	F: 'static + Send + FnOnce() -> T,
-	T: 'static + Send,
-{
-	if THREAD_ACTIVE.load(Ordering::SeqCst) < *THREAD.get().unwrap() - 1 {
-		THREAD_ACTIVE.fetch_add(1, Ordering::SeqCst);
-
-		threads_pool.push(thread::spawn(f));
-	} else {
-		f();
-	}
-}
-pub fn deactivate_threads<T>(threads: Vec<JoinHandle<T>>) -> Vec<T> {
-	let ts_count = threads.len() as u16;
-	let rs = threads.into_iter().map(|t| t.join().unwrap()).collect();
-
-	THREAD_ACTIVE.fetch_sub(ts_count, Ordering::SeqCst);
+use crate::{
+	cli::{IndentSymbol, Mode, SharedInitiator},
+	prelude::*,
+};
 
-	rs
+/// Context which is shared by the analyzer and the resolver.
+#[derive(Clone, Debug)]
+pub struct Shared {
+	/// Features to process.
+	///
+	/// Empty if they are left to the analyzer to discover.
+	pub features: Vec<String>,
+	pub mode: Mode,
+	/// Prefix of a new entry of a feature array, e.g. `"\n\t"`.
+	pub indentation: String,
+	// Bounded worker pool, which never exceeds the `--thread`.
+	pool: Arc<ThreadPool>,
 }
-
-pub struct Shared;
 impl Shared {
-	pub fn initialize(initiator: SharedInitiator) -> Self {
-		FEATURES.set(initiator.features).unwrap();
-		THREAD.set(initiator.thread).unwrap();
-		MODE.set(initiator.mode).unwrap();
-
+	pub fn initialize(initiator: SharedInitiator) -> Result<Self> {
 		let indentation = match initiator.indent_symbol {
 			IndentSymbol::Tab => "\n\t".into(),
 			IndentSymbol::Whitespace => format!("\n{}", " ".repeat(initiator.indent_size)),
 		};
+		let pool = ThreadPoolBuilder::new()
+			.num_threads(initiator.thread.into())
+			.thread_name(|i| format!("cargo-featalign-{i}"))
+			.build()?;
+
+		Ok(Self {
+			features: initiator.features,
+			mode: initiator.mode,
+			indentation,
+			pool: Arc::new(pool),
+		})
+	}
 
-		INDENTATION.set(indentation).unwrap();
-
-		Self
+	/// Run the given operation inside the worker pool, where the parallel iterators are executed.
+	pub fn install<F, T>(&self, f: F) -> T
+	where
+		F: Send + FnOnce() -> T,
+		T: Send,
+	{
+		self.pool.install(f)
 	}
 }
@@ -1,12 +1,15 @@
 // std
 use std::mem;
 // crates.io
-use toml_edit::{Formatted, RawString, Table, Value, visit_mut::VisitMut};
-// cargo-featalign
-use crate::shared::INDENTATION;
+use toml_edit::{Formatted, Item, RawString, Table, Value, visit_mut::VisitMut};
 
 #[derive(Debug)]
-pub struct SortVisitor(pub Vec<String>);
+pub struct SortVisitor {
+	/// Features whose required features are sorted.
+	pub features: Vec<String>,
+	/// Prefix of an entry which has no decor.
+	pub indentation: String,
+}
 impl VisitMut for SortVisitor {
 	fn visit_table_mut(&mut self, node: &mut Table) {
 		fn sort(mut v: Vec<Formatted<String>>) -> Vec<Formatted<String>> {
@@ -66,41 +69,54 @@ impl VisitMut for SortVisitor {
 			v_chunks.concat()
 		}
 
-		if let Some(v) = node.get_mut("features") {
-			let t = v.as_table_mut().unwrap();
-			let pfs = mem::take(&mut self.0);
+		// A multi-line array doesn't fit in `features = { .. }`.
+		let inline = node.get("features").is_some_and(Item::is_inline_table);
+		let Some(t) = node.get_mut("features").and_then(Item::as_table_like_mut) else {
+			return;
+		};
+		let indentation = if inline { " " } else { &self.indentation };
+		let pfs = mem::take(&mut self.features);
+
+		pfs.into_iter().for_each(|f| {
+			let Some(rfs) = t.get_mut(&f).and_then(Item::as_array_mut) else {
+				return;
+			};
+
+			// Cargo rejects the other values anyway.
+			if !rfs.iter().all(Value::is_str) {
+				return;
+			}
 
-			pfs.into_iter().for_each(|f| {
-				if let Some(rfs) = t.get_mut(&f) {
-					let rfs = rfs.as_array_mut().unwrap();
-					let rfs_ = mem::take(rfs);
+			let rfs_ = mem::take(rfs);
 
-					rfs.set_trailing(rfs_.trailing().to_owned());
+			rfs.set_trailing(rfs_.trailing().to_owned());
 
-					let rfs_values = rfs_
-						.into_iter()
-						.map(|v| if let Value::String(s) = v { s } else { unreachable!() })
-						.collect::<Vec<_>>();
+			let rfs_values = rfs_
+				.into_iter()
+				.map(|v| if let Value::String(s) = v { s } else { unreachable!() })
+				.collect::<Vec<_>>();
 
-					sort(rfs_values).into_iter().for_each(|f| {
-						let v = if f.decor().prefix().is_none() {
-							Value::String(f).decorated(INDENTATION.get().unwrap(), "")
-						} else {
-							Value::String(f)
-						};
+			sort(rfs_values).into_iter().for_each(|f| {
+				let v = if f.decor().prefix().is_none() {
+					Value::String(f).decorated(indentation, "")
+				} else {
+					Value::String(f)
+				};
 
-						rfs.push_formatted(v);
-					});
+				rfs.push_formatted(v);
+			});
 
-					if !rfs.is_empty() {
-						rfs.set_trailing_comma(true);
+			if inline {
+				if let Some(v) = rfs.get_mut(0) {
+					v.decor_mut().set_prefix("");
+				}
+			} else if !rfs.is_empty() {
+				rfs.set_trailing_comma(true);
 
-						if rfs.trailing().as_str().map(|s| s.is_empty()).unwrap_or(true) {
-							rfs.set_trailing("\n");
-						}
-					}
+				if rfs.trailing().as_str().map(|s| s.is_empty()).unwrap_or(true) {
+					rfs.set_trailing("\n");
 				}
-			});
-		}
+			}
+		});
 	}
 }
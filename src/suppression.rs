@@ -0,0 +1,151 @@
+// std
+use std::{
+	fmt::{Display, Formatter, Result as FmtResult},
+	fs,
+	path::{Path, PathBuf},
+	sync::atomic::{AtomicBool, Ordering},
+};
+// crates.io
+use once_cell::sync::Lazy;
+use regex::Regex;
+use toml_edit::{Decor, ImDocument, Item, RawString, TableLike};
+// cargo-featalign
+use crate::{analyzer::FeatureValue, locator::Position, prelude::*};
+
+static ALLOW_REGEX: Lazy<Regex> =
+	Lazy::new(|| Regex::new(r"#\s*featalign:\s*allow\(([^)]*)\)").unwrap());
+
+/// Inline suppression comment, e.g. `# featalign: allow(pallet-a/std, pallet-b)`.
+///
+/// It's read from the `[features]` arrays and the dependency declarations, under the
+/// `[dependencies]`, the `[build-dependencies]` or their `[target.*]` variants.
+/// An entry without the feature suppresses every problem of the dependency, and the `default`
+/// feature suppresses the `default-features` problem.
+#[derive(Debug)]
+pub struct Suppression {
+	pub manifest_path: PathBuf,
+	pub line: usize,
+	pub dependency: String,
+	pub feature: Option<String>,
+	used: AtomicBool,
+}
+impl Suppression {
+	pub fn load(manifest_path: &Path) -> Result<Vec<Self>> {
+		let s = fs::read_to_string(manifest_path)?;
+		let d = ImDocument::parse(s.as_str())?;
+		let mut rs = Vec::new();
+
+		if let Some(fs) = d.get("features").and_then(Item::as_table_like) {
+			for (k, v) in fs.iter() {
+				push_key_decor(&mut rs, fs, k);
+
+				if let Some(a) = v.as_array() {
+					a.iter().for_each(|v| push_decor(&mut rs, v.decor()));
+					rs.push(a.trailing());
+				}
+			}
+		}
+
+		push_dependencies(&mut rs, d.as_table());
+
+		if let Some(ts) = d.get("target").and_then(Item::as_table_like) {
+			ts.iter()
+				.filter_map(|(_, t)| t.as_table_like())
+				.for_each(|t| push_dependencies(&mut rs, t));
+		}
+
+		let mut ss = Vec::new();
+
+		for r in rs {
+			let Some(span) = r.span() else {
+				continue;
+			};
+
+			for c in ALLOW_REGEX.captures_iter(&s[span.clone()]) {
+				let m = c.get(1).unwrap();
+				let line = Position::of(&s, span.start + m.start()).line;
+
+				for e in m.as_str().split(',').map(str::trim).filter(|e| !e.is_empty()) {
+					let (dependency, feature) = match FeatureValue::parse(e) {
+						FeatureValue::Feature(d) | FeatureValue::Dependency(d) => (d, None),
+						FeatureValue::DependencyFeature { dependency, feature, .. } =>
+							(dependency, Some(feature.to_owned())),
+					};
+
+					ss.push(Self {
+						manifest_path: manifest_path.to_owned(),
+						line,
+						dependency: dependency.to_owned(),
+						feature,
+						used: AtomicBool::new(false),
+					});
+				}
+			}
+		}
+
+		Ok(ss)
+	}
+
+	/// Check if the problem is suppressed, and mark the suppression as used if so.
+	pub fn suppress(&self, dependency: &str, alias: &str, feature: &str) -> bool {
+		let is_match = self.is_match(dependency, alias, feature);
+
+		if is_match {
+			self.used.store(true, Ordering::Relaxed);
+		}
+
+		is_match
+	}
+
+	/// The dependency is matched by either the package name or the alias.
+	pub fn is_match(&self, dependency: &str, alias: &str, feature: &str) -> bool {
+		(self.dependency == dependency || self.dependency == alias)
+			&& self.feature.as_deref().is_none_or(|f| f == feature)
+	}
+
+	pub fn is_used(&self) -> bool {
+		self.used.load(Ordering::Relaxed)
+	}
+}
+impl Display for Suppression {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "{}:{}: allow({}", self.manifest_path.display(), self.line, self.dependency)?;
+
+		if let Some(feature) = &self.feature {
+			write!(f, "/{feature}")?;
+		}
+
+		write!(f, ")")
+	}
+}
+
+// The `[dependencies]` and the `[build-dependencies]` of the table, the same as the ones which
+// are fixed.
+fn push_dependencies<'a>(raws: &mut Vec<&'a RawString>, table: &'a dyn TableLike) {
+	for ds in ["dependencies", "build-dependencies", "build_dependencies"]
+		.iter()
+		.filter_map(|k| table.get(k).and_then(Item::as_table_like))
+	{
+		for (k, v) in ds.iter() {
+			push_key_decor(raws, ds, k);
+
+			match v {
+				// `[dependencies.x]`.
+				Item::Table(t) => push_decor(raws, t.decor()),
+				Item::Value(v) => push_decor(raws, v.decor()),
+				_ => (),
+			}
+		}
+	}
+}
+
+fn push_key_decor<'a>(raws: &mut Vec<&'a RawString>, table: &'a dyn TableLike, key: &str) {
+	if let Some(k) = table.key(key) {
+		push_decor(raws, k.leaf_decor());
+	}
+}
+
+fn push_decor<'a>(raws: &mut Vec<&'a RawString>, decor: &'a Decor) {
+	raws.extend(decor.prefix());
+	raws.extend(decor.suffix());
+}
@@ -1,62 +1,606 @@
 // std
-use std::fs;
+use std::{fs, path::Path};
 // crates.io
+use cargo_metadata::PackageId;
 use toml_edit::{DocumentMut, visit_mut::VisitMut};
 // cargo-featalign
 use crate::{
-	analyzer::Analyzer,
-	cli::{AnalyzerInitiator, IndentSymbol, Mode, ResolverInitiator, SharedInitiator},
-	resolver::Resolver,
+	analyzer::{
+		Analyzer, DanglingReference, FeatureHole, FeatureValue, IgnoreRule, Problem, ProblemCrate,
+		Problems,
+	},
+	cli::{
+		AnalyzerInitiator, Cli, CommandFactory, Format, FromArgMatches, IndentSymbol, Mode,
+		ReporterInitiator, ResolverInitiator, SharedInitiator,
+	},
+	config::Config,
+	locator::{Location, Locator},
+	reporter::Reporter,
+	resolver::{Resolution, Resolver},
 	shared::Shared,
 	sorter::SortVisitor,
 };
 
 #[test]
 fn cargo_featalign_should_work() {
-	Shared::initialize(SharedInitiator {
-		features: ["std", "runtime-benchmarks", "try-runtime", "empty"]
-			.iter()
-			.copied()
-			.map(Into::into)
-			.collect(),
-		thread: 32,
-		mode: Mode::DryRun2,
-		indent_symbol: IndentSymbol::Tab,
-		indent_size: 4,
+	let analyzer =
+		analyzer_of("mock", &["std", "runtime-benchmarks", "try-runtime", "empty"], |_| ());
+	let analysis = analyzer.analyze(-1);
+	let ss = &analysis.unused_suppressions;
+
+	assert_eq!(ss.len(), 2);
+	assert!(ss[0].ends_with("mock/suppression/a/Cargo.toml:23: allow(pallet-c/std)"));
+	assert!(ss[1].ends_with("mock/suppression/a/Cargo.toml:25: allow(pallet-b/std)"));
+	// `suppression-a` isn't analyzed, although its suppressions apply to its feature holes.
+	assert!(analyzer.analyze(0).unused_suppressions.is_empty());
+
+	Resolver::initialize(
+		analyzer.shared().to_owned(),
+		ResolverInitiator { sort: true, prune: false },
+	)
+	.resolve(analysis.problems)
+	.unwrap();
+
+	assert_swaps(&[
+		"mock",
+		"mock/default/a",
+		"mock/inherited/a",
+		"mock/inherited/b",
+		"mock/nested/a",
+		"mock/nested/b",
+		"mock/suppression/a",
+	]);
+}
+
+#[test]
+fn problems_should_be_sorted() {
+	let analyzer = analyzer_of("mock", &["try-runtime", "std", "runtime-benchmarks"], |_| ());
+	let problems = analyzer.analyze(-1).problems;
+	let s = serde_json::to_string(&problems).unwrap();
+
+	// `path+file:///x#name@version`.
+	assert!(problems.keys().is_sorted_by_key(|id| id.repr.rsplit('#').next()));
+
+	for pcs in problems.values() {
+		assert!(pcs.is_sorted_by(|a, b| a.alias <= b.alias));
+
+		for pc in pcs {
+			if let Problem::MissingFeatures(fs) = &pc.problem {
+				assert!(fs.is_sorted());
+			}
+		}
+	}
+
+	(0..8).for_each(|_| {
+		assert_eq!(serde_json::to_string(&analyzer.analyze(-1).problems).unwrap(), s)
 	});
-	Analyzer::initialize(AnalyzerInitiator {
-		manifest_path: "mock".into(),
-		workspace_only: true,
-		default_std: true,
-		ignore: Vec::new(),
-		non_default_std: Vec::new(),
+}
+
+#[test]
+fn dependency_paths_should_work() {
+	let analyze = |max_dependency_paths| {
+		let analyzer =
+			analyzer_of("mock", &["std"], |a| a.max_dependency_paths = max_dependency_paths);
+		let problems = analyzer.analyze(-1).problems;
+		let (_, pcs) =
+			problems.iter().find(|(id, _)| id.repr.ends_with("#nested-a@0.0.0")).unwrap();
+
+		pcs[0]
+			.dependency_paths
+			.iter()
+			.map(|p| {
+				p.iter()
+					.map(|id| analyzer.package(id).unwrap().name.as_str())
+					.collect::<Vec<_>>()
+					.join(">")
+			})
+			.collect::<Vec<_>>()
+	};
+
+	assert_eq!(
+		analyze(4),
+		["mock-runtime>primitive-a>nested-a", "mock-runtime>optional-a>primitive-a>nested-a"]
+	);
+	assert_eq!(analyze(1), ["mock-runtime>primitive-a>nested-a"]);
+}
+
+#[test]
+fn dangling_references_should_work() {
+	let analyzer =
+		analyzer_of("mock/dangling", &["std", "runtime-benchmarks", "try-runtime"], |a| {
+			a.workspace_only = false
+		});
+	let problems = analyzer.analyze(-1).problems;
+
+	assert_eq!(problems.len(), 1);
+
+	let pc = &problems[0][0];
+
+	assert_eq!(pc.alias, "dangling-c");
+	assert_eq!(
+		pc.problem,
+		Problem::DanglingReferences(vec![
+			DanglingReference {
+				feature: "runtime-benchmarks".into(),
+				value: "dangling-c/runtime-benchmarks".into(),
+			},
+			// Every feature is checked, not only the aligned ones.
+			DanglingReference { feature: "serde".into(), value: "dangling-c/serde".into() },
+			DanglingReference { feature: "try-runtime".into(), value: "dangling-c/serde".into() },
+		])
+	);
+	assert_eq!(
+		pc.locations.iter().map(|l| (l.start.line, l.start.column)).collect::<Vec<_>>(),
+		[(20, 23), (27, 23), (23, 2)]
+	);
+
+	// Another version of the same crate, which is renamed.
+	let pc = &problems[0][1];
+
+	assert_eq!(pc.alias, "dangling-c-v2");
+	assert_eq!(
+		pc.problem,
+		Problem::DanglingReferences(vec![DanglingReference {
+			feature: "try-runtime".into(),
+			value: "dangling-c-v2/try-runtime".into(),
+		}])
+	);
+
+	Resolver::initialize(
+		analyzer.shared().to_owned(),
+		ResolverInitiator { sort: false, prune: false },
+	)
+	.resolve(problems)
+	.unwrap();
+
+	assert_swaps(&["mock/dangling/b"]);
+}
+
+#[test]
+fn feature_holes_should_work() {
+	let analyzer = analyzer_of("mock/hole", &["std", "runtime-benchmarks"], |_| ());
+	let problems = analyzer.analyze(-1).problems;
+	let pcs = &problems[0];
+
+	// `hole-e` is out of the workspace.
+	assert_eq!(pcs.len(), 2);
+	assert!(pcs.iter().all(|pc| pc.alias != "hole-e"));
+	assert_eq!(
+		pcs[1].problem,
+		Problem::FeatureHoles(vec![
+			FeatureHole {
+				feature: "runtime-benchmarks".into(),
+				entries: vec!["hole-c?/runtime-benchmarks".into()],
+			},
+			FeatureHole { feature: "std".into(), entries: vec!["hole-c?/std".into()] },
+		])
+	);
+
+	// The holes are filled in the dependencies' manifests.
+	Resolver::initialize(
+		analyzer.shared().to_owned(),
+		ResolverInitiator { sort: false, prune: false },
+	)
+	.resolve(problems)
+	.unwrap();
+
+	assert_swaps(&["mock/hole", "mock/hole/b", "mock/hole/d"]);
+}
+
+#[test]
+fn host_dependencies_should_work() {
+	let analyze = |host_features: &[&str]| {
+		analyzer_of("mock/host", &["std"], |a| {
+			a.host_features = host_features.iter().map(|f| (*f).into()).collect()
+		})
+		.analyze(-1)
+		.problems
+		.values()
+		.flatten()
+		.map(|pc| (pc.alias.clone(), pc.host, pc.problem.clone()))
+		.collect::<Vec<_>>()
+	};
+	let std = || Problem::MissingFeatures(vec!["std".into()]);
+
+	// The build dependencies and the proc-macro crates are excluded by default.
+	assert_eq!(analyze(&[]), [("host-lib".into(), false, std())]);
+	// Including the dependencies of the proc-macro crates.
+	assert_eq!(
+		analyze(&["std"]),
+		[
+			("host-build".into(), true, std()),
+			("host-lib".into(), false, std()),
+			("host-macro".into(), true, std()),
+			("host-leaf".into(), true, std()),
+		]
+	);
+
+	let analysis = analyzer_of("mock/host", &["std"], |a| {
+		a.host_features = vec!["std".into(), "serde".into()]
 	})
 	.analyze(-1);
-	Resolver::initialize(ResolverInitiator { sort: true }).resolve().unwrap();
 
-	["mock", "mock/nested/a", "mock/nested/b"].iter().for_each(|p| {
-		let expect_p = format!("{}/Cargo.toml.expect", p);
-		let dry_run_p = format!("{}/Cargo.toml.cargo-featalign.swap", p);
+	// `# featalign: allow(host-build/serde)` under the `[build-dependencies]`.
+	assert!(analysis.unused_suppressions.is_empty());
+	assert!(
+		analysis
+			.problems
+			.values()
+			.flatten()
+			.any(|pc| pc.alias == "host-build" && pc.problem == std())
+	);
+}
 
-		let expect_s = fs::read_to_string(expect_p).unwrap();
-		let dry_run_s = fs::read_to_string(&dry_run_p).unwrap();
+#[test]
+fn prune_should_work() {
+	let resolver = Resolver::initialize(
+		shared_of(&[], Mode::DryRun2),
+		ResolverInitiator { sort: false, prune: true },
+	);
 
-		assert_eq!(expect_s, dry_run_s);
+	// Cargo fails to load these manifests.
+	assert_eq!(resolver.prune(Path::new("mock/prune")).unwrap().len(), 3);
+	assert_swaps(&["mock/prune", "mock/prune/a", "mock/prune/x/b"]);
+	assert!(!Path::new("mock/prune/x/excluded/Cargo.toml.cargo-featalign.swap").exists());
 
-		fs::remove_file(dry_run_p).unwrap();
-	});
+	// The check mode only reports the stale entries.
+	let resolver = Resolver::initialize(
+		shared_of(&[], Mode::Check),
+		ResolverInitiator { sort: false, prune: true },
+	);
+
+	assert_eq!(resolver.prune(Path::new("mock/prune")).unwrap().len(), 3);
+	assert!(!Path::new("mock/prune/Cargo.toml.cargo-featalign.swap").exists());
 }
 
 #[test]
-fn sort_visitor_should_work() {
-	Shared::initialize(SharedInitiator {
-		features: ["f", "g", "empty"].iter().copied().map(Into::into).collect(),
-		thread: 32,
-		mode: Mode::DryRun2,
-		indent_symbol: IndentSymbol::Tab,
-		indent_size: 4,
+fn features_forms_should_work() {
+	let analyzer = analyzer_of("mock/form", &["std", "try-runtime"], |_| ());
+
+	// The `[features]` of the dependencies are written as the dotted keys and the inline table.
+	Resolver::initialize(
+		analyzer.shared().to_owned(),
+		ResolverInitiator { sort: false, prune: false },
+	)
+	.resolve(analyzer.analyze(-1).problems)
+	.unwrap();
+
+	assert_swaps(&["mock/form", "mock/form/dotted", "mock/form/inline"]);
+}
+
+#[test]
+fn unfixable_problems_should_be_reported() {
+	let resolver = Resolver::initialize(
+		shared_of(&["std", "runtime-benchmarks"], Mode::DryRun2),
+		ResolverInitiator { sort: false, prune: false },
+	);
+	let id = local_id_of("mock/form/unfixable", "form-unfixable");
+	let problems = Problems::from_iter([(
+		id.clone(),
+		vec![
+			problem_crate_of(&id, "form-undeclared", Problem::DefaultFeaturesEnabled),
+			problem_crate_of(
+				&id,
+				"form-leaf",
+				Problem::MissingFeatures(vec!["runtime-benchmarks".into()]),
+			),
+		],
+	)]);
+	let es = resolver.edit(problems).unwrap();
+
+	assert_eq!(es.len(), 1);
+	assert_eq!(
+		es[0].unfixable,
+		[
+			"failed to disable the default features of `form-undeclared`, the declaration isn't found",
+			"failed to add `form-undeclared/std` to the `std` feature, which isn't an array",
+		]
+	);
+	// The others are still fixed.
+	assert!(es[0].fixed.contains("runtime-benchmarks = [\n\t\"form-leaf/runtime-benchmarks\",\n]"));
+}
+
+#[test]
+fn external_crates_should_be_skipped() {
+	let analyzer = analyzer_of("mock/external", &["alloc"], |a| a.workspace_only = false);
+	let mut problems = analyzer.analyze(0).problems;
+	let pc = &problems[0][0];
+
+	// `thread_local` doesn't define `alloc`, while `once_cell` does.
+	assert!(pc.id.repr.starts_with("registry+https://github.com/rust-lang/crates.io-index#"));
+	assert_eq!(
+		pc.problem,
+		Problem::FeatureHoles(vec![FeatureHole {
+			feature: "alloc".into(),
+			entries: vec!["once_cell/alloc".into()],
+		}])
+	);
+
+	let git_id = PackageId { repr: "git+https://github.com/serde-rs/serde#serde@1.0.0".into() };
+
+	problems.insert(
+		git_id.clone(),
+		vec![problem_crate_of(
+			&git_id,
+			"serde_derive",
+			Problem::MissingFeatures(vec!["std".into()]),
+		)],
+	);
+
+	let Resolution { edits, skipped } = Resolver::initialize(
+		analyzer.shared().to_owned(),
+		ResolverInitiator { sort: false, prune: false },
+	)
+	.resolve(problems)
+	.unwrap();
+
+	// Only the local crate is edited, and nothing is changed.
+	assert_eq!(skipped, [git_id]);
+	assert_eq!(edits.len(), 1);
+	assert_eq!(
+		edits[0].unfixable,
+		["failed to fill the feature holes of `thread_local`, which is from \
+			 `registry+https://github.com/rust-lang/crates.io-index`"]
+	);
+	assert!(!edits[0].is_changed());
+}
+
+#[test]
+fn reporter_should_work() {
+	let analyzer = analyzer_of("mock/virtual", &["std"], |a| a.default_std = false);
+	let resolver = Resolver::initialize(
+		analyzer.shared().to_owned(),
+		ResolverInitiator { sort: false, prune: false },
+	);
+	let problems = analyzer.analyze(0).problems;
+	let r = Reporter::initialize(ReporterInitiator { format: Format::Text })
+		.report(&analyzer, &resolver, &problems)
+		.unwrap()
+		.replace(env!("CARGO_MANIFEST_DIR"), "");
+
+	assert_eq!(
+		r,
+		"\
+virtual-a 0.0.0 (/mock/virtual/a/Cargo.toml)
+  via virtual-a 0.0.0
+  virtual-c: missing `virtual-c/std` (17:1)
+
+virtual-b 0.0.0 (/mock/virtual/b/Cargo.toml)
+  via virtual-b 0.0.0
+  virtual-c: missing `virtual-c/std` (17:1)
+
+found 2 problems in 2 crates: 0 default-features-enabled, 2 missing-features, 0 dangling-references, 0 feature-holes"
+	);
+
+	let r = Reporter::initialize(ReporterInitiator { format: Format::Json })
+		.report(&analyzer, &resolver, &problems)
+		.unwrap();
+
+	assert_eq!(r, serde_json::to_string(&problems).unwrap());
+
+	let r = Reporter::initialize(ReporterInitiator { format: Format::Github })
+		.report(&analyzer, &resolver, &problems)
+		.unwrap();
+
+	assert_eq!(
+		r,
+		"\
+::error file=a/Cargo.toml,line=17,col=1,endLine=17,endColumn=13,title=missing-features::`virtual-c/std` is missing from the `std` feature
+::error file=b/Cargo.toml,line=17,col=1,endLine=17,endColumn=13,title=missing-features::`virtual-c/std` is missing from the `std` feature"
+	);
+}
+
+#[test]
+fn sarif_report_should_work() {
+	let analyzer = analyzer_of("mock/default/a", &["std"], |_| ());
+	let resolver = Resolver::initialize(
+		analyzer.shared().to_owned(),
+		ResolverInitiator { sort: false, prune: false },
+	);
+	let problems = analyzer.analyze(0).problems;
+	let r = Reporter::initialize(ReporterInitiator { format: Format::Sarif })
+		.report(&analyzer, &resolver, &problems)
+		.unwrap();
+	let r = serde_json::from_str::<serde_json::Value>(&r).unwrap();
+	let rs = r["runs"][0]["results"].as_array().unwrap();
+
+	assert_eq!(r["version"], "2.1.0");
+	assert_eq!(rs.len(), 2);
+	assert!(rs.iter().all(|r| r["ruleId"] == "default-features-enabled"));
+
+	let l = &rs[0]["locations"][0]["physicalLocation"];
+
+	assert_eq!(l["artifactLocation"]["uri"], "default/a/Cargo.toml");
+	assert_eq!(l["region"]["startLine"], 13);
+	assert_eq!(
+		rs[1]["locations"][0]["physicalLocation"]["region"],
+		serde_json::json!({ "startLine": 18, "startColumn": 1, "endLine": 18, "endColumn": 41 })
+	);
+
+	let c = &rs[1]["fixes"][0]["artifactChanges"][0];
+
+	assert_eq!(c["artifactLocation"]["uri"], "default/a/Cargo.toml");
+	assert_eq!(
+		c["replacements"][0],
+		serde_json::json!({
+			"deletedRegion": { "startLine": 18, "startColumn": 1, "endLine": 19, "endColumn": 1 },
+			"insertedContent": {
+				"text": "general-b = { path = \"../../general/b\", default-features = false }\n"
+			},
+		})
+	);
+}
+
+#[test]
+fn pure_workspace_should_work() {
+	let analyzer = analyzer_of("mock/virtual", &["std"], |a| a.default_std = false);
+
+	Resolver::initialize(
+		analyzer.shared().to_owned(),
+		ResolverInitiator { sort: true, prune: false },
+	)
+	.resolve(analyzer.analyze(0).problems)
+	.unwrap();
+
+	assert_swaps(&["mock/virtual/a", "mock/virtual/b"]);
+}
+
+#[test]
+fn config_should_work() {
+	let c = Config::load("mock/config/Cargo.toml".as_ref()).unwrap();
+
+	assert_eq!(
+		c,
+		Config {
+			features: Some(vec!["std".into()]),
+			indent_symbol: Some(IndentSymbol::Whitespace),
+			default_std: Some(true),
+			non_default_std: Some(vec!["general-c".into()]),
+			sort: Some(true),
+			..Default::default()
+		}
+	);
+
+	let matches = Cli::command().get_matches_from([
+		"cargo-featalign",
+		"mock/config",
+		"--features",
+		"runtime-benchmarks",
+		"--indent-symbol",
+		"tab",
+	]);
+	let mut cli = Cli::from_arg_matches(&matches).unwrap();
+
+	c.apply(&mut cli, &matches);
+
+	assert_eq!(cli.shared_initiator.features, ["runtime-benchmarks"]);
+	assert_eq!(cli.shared_initiator.indent_symbol, IndentSymbol::Tab);
+	assert!(cli.analyzer_initiator.default_std);
+	assert_eq!(cli.analyzer_initiator.non_default_std, ["general-c"]);
+	assert!(cli.resolver_initiator.sort);
+
+	// The boolean flags which are enabled by the file can be turned off.
+	let matches = Cli::command().get_matches_from([
+		"cargo-featalign",
+		"mock/config",
+		"--default-std=false",
+		"--sort=false",
+		"--workspace-only",
+	]);
+	let mut cli = Cli::from_arg_matches(&matches).unwrap();
+
+	Config::load("mock/config/Cargo.toml".as_ref()).unwrap().apply(&mut cli, &matches);
+
+	assert!(!cli.analyzer_initiator.default_std);
+	assert!(!cli.resolver_initiator.sort);
+	assert!(cli.analyzer_initiator.workspace_only);
+}
+
+#[test]
+fn discover_features_should_work() {
+	let discover = |include_features: &[&str], exclude_features: &[&str]| {
+		analyzer_of("mock", &[], |a| {
+			a.include_features = include_features.iter().map(|f| (*f).into()).collect();
+			a.exclude_features = exclude_features.iter().map(|f| (*f).into()).collect();
+		})
+		.shared()
+		.features
+		.clone()
+	};
+
+	assert_eq!(discover(&[], &[]), ["empty", "runtime-benchmarks", "std", "try-runtime"]);
+	assert_eq!(discover(&["*-*"], &["try-*"]), ["runtime-benchmarks"]);
+}
+
+#[test]
+fn locator_should_work() {
+	let position_of = |l: Location| (l.start.line, l.start.column, l.end.line, l.end.column);
+	let l = Locator::load("mock/default/a/Cargo.toml".as_ref());
+
+	// `[dependencies.general-a]`.
+	assert_eq!(position_of(l.dependency("general-a")), (13, 1, 14, 25));
+	assert_eq!(position_of(l.dependency("general-b")), (18, 1, 18, 41));
+	assert_eq!(position_of(l.feature("std", "general-a")), (22, 1, 22, 13));
+	// Fall back to the `[features]` header.
+	assert_eq!(position_of(l.feature("try-runtime", "general-a")).0, 20);
+
+	let l = Locator::load("mock/Cargo.toml".as_ref());
+
+	assert_eq!(position_of(l.workspace_dependency("nested-c")), (111, 1, 111, 33));
+	// Fall back to the start of the file.
+	assert_eq!(position_of(l.workspace_dependency("x")), (1, 1, 1, 1));
+}
+
+#[test]
+fn feature_value_parse_should_work() {
+	assert_eq!(FeatureValue::parse("std"), FeatureValue::Feature("std"));
+	assert_eq!(FeatureValue::parse("dep:general-a"), FeatureValue::Dependency("general-a"));
+	assert_eq!(
+		FeatureValue::parse("general-a/std"),
+		FeatureValue::DependencyFeature { dependency: "general-a", feature: "std", weak: false }
+	);
+	assert_eq!(
+		FeatureValue::parse("general-a?/std"),
+		FeatureValue::DependencyFeature { dependency: "general-a", feature: "std", weak: true }
+	);
+
+	// Formatting is the inverse of parsing.
+	["std", "dep:general-a", "general-a/std", "general-a?/std"]
+		.iter()
+		.for_each(|s| assert_eq!(FeatureValue::parse(s).to_string(), *s));
+}
+
+#[test]
+fn ignore_rule_should_work() {
+	let r = "pallet-x/runtime-benchmarks".parse::<IgnoreRule>().unwrap();
+
+	assert!(r.is_match("mock-runtime", "pallet-x", "pallet-x", "runtime-benchmarks"));
+	assert!(!r.is_match("mock-runtime", "pallet-x", "pallet-x", "std"));
+
+	let r = "z:y".parse::<IgnoreRule>().unwrap();
+
+	assert!(r.is_match("z", "general-y", "y", "std"));
+	assert!(r.is_match("z", "y", "y", "default"));
+	assert!(!r.is_match("x", "y", "y", "std"));
+
+	let r = "*-runtime:pallet-*/try-*".parse::<IgnoreRule>().unwrap();
+
+	assert!(r.is_match("mock-runtime", "pallet-a", "a", "try-runtime"));
+	assert!(!r.is_match("mock-runtime", "primitive-a", "pallet-a", "std"));
+	assert!(!r.is_match("runtime-mock", "pallet-a", "pallet-a", "try-runtime"));
+
+	assert!("z:".parse::<IgnoreRule>().is_err());
+	assert!("y/".parse::<IgnoreRule>().is_err());
+
+	let analyzer = analyzer_of("mock", &["std", "runtime-benchmarks", "try-runtime"], |a| {
+		a.ignore_rules = ["pallet-d/runtime-benchmarks", "general-c/default", "nested-a:nested-d"]
+			.iter()
+			.map(|r| r.parse().unwrap())
+			.collect()
 	});
+	let problems = analyzer.analyze(-1).problems;
+	let problems_of = |parent: &str, alias: &str| {
+		problems
+			.iter()
+			.filter(|(id, _)| analyzer.package(id).unwrap().name == parent)
+			.flat_map(|(_, pcs)| pcs)
+			.filter(|pc| pc.alias == alias)
+			.map(|pc| pc.problem.clone())
+			.collect::<Vec<_>>()
+	};
 
+	assert_eq!(
+		problems_of("mock-runtime", "pallet-d"),
+		[Problem::MissingFeatures(vec!["std".into(), "try-runtime".into()])]
+	);
+	assert_eq!(problems_of("mock-runtime", "general-c"), []);
+	assert_eq!(problems_of("nested-a", "nested-d"), []);
+	// Only the problems of `nested-a` are disregarded.
+	assert_eq!(problems_of("inherited-a", "nested-d"), [Problem::DefaultFeaturesEnabled]);
+}
+
+#[test]
+fn sort_visitor_should_work() {
 	let s = r#"
 [features]
 f = [
@@ -105,7 +649,10 @@ empty = []
 "#;
 
 	let mut d = s.parse::<DocumentMut>().unwrap();
-	let mut s = SortVisitor(["f", "g", "empty"].iter().map(|s| (*s).into()).collect());
+	let mut s = SortVisitor {
+		features: ["f", "g", "empty"].iter().map(|s| (*s).into()).collect(),
+		indentation: "\n\t".into(),
+	};
 	s.visit_document_mut(&mut d);
 
 	assert_eq!(
@@ -152,3 +699,62 @@ empty = []
 "#
 	);
 }
+
+// The shared context of the tests.
+fn shared_of(features: &[&str], mode: Mode) -> Shared {
+	Shared::initialize(SharedInitiator {
+		features: features.iter().map(|f| (*f).into()).collect(),
+		mode,
+		..Default::default()
+	})
+	.unwrap()
+}
+
+// Analyzer of the mock in the `DryRun2` mode, the defaults of the initiator are tweaked by
+// `tweak`.
+fn analyzer_of<F>(path: &str, features: &[&str], tweak: F) -> Analyzer
+where
+	F: FnOnce(&mut AnalyzerInitiator),
+{
+	let mut initiator = AnalyzerInitiator {
+		manifest_path: path.into(),
+		workspace_only: true,
+		default_std: true,
+		..Default::default()
+	};
+
+	tweak(&mut initiator);
+
+	Analyzer::initialize(shared_of(features, Mode::DryRun2), initiator).unwrap()
+}
+
+// `path+file:///x/mock/a#a@0.0.0`.
+fn local_id_of(path: &str, name: &str) -> PackageId {
+	PackageId { repr: format!("path+file://{}/{path}#{name}@0.0.0", env!("CARGO_MANIFEST_DIR")) }
+}
+
+fn problem_crate_of(id: &PackageId, alias: &str, problem: Problem) -> ProblemCrate {
+	ProblemCrate {
+		id: id.to_owned(),
+		alias: alias.into(),
+		optional: false,
+		inherited: false,
+		host: false,
+		dependency_paths: Vec::new(),
+		locations: Vec::new(),
+		problem,
+	}
+}
+
+// Compare the swap file of each directory with its `Cargo.toml.expect`, then remove the swap file.
+fn assert_swaps(directories: &[&str]) {
+	for d in directories {
+		let swap_p = format!("{d}/Cargo.toml.cargo-featalign.swap");
+		let expect_s = fs::read_to_string(format!("{d}/Cargo.toml.expect")).unwrap();
+		let swap_s = fs::read_to_string(&swap_p).unwrap();
+
+		fs::remove_file(&swap_p).unwrap();
+
+		assert_eq!(expect_s, swap_s, "{d}");
+	}
+}
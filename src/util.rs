@@ -1,86 +1,91 @@
 // std
 use std::{
 	borrow::Cow,
+	fs,
 	path::{Path, PathBuf},
 };
 // crates.io
-use cargo_metadata::{Metadata, Node, Package, PackageId, Resolve};
 use imara_diff::{Algorithm, UnifiedDiffBuilder, intern::InternedInput};
+use toml_edit::{DocumentMut, Item};
+// cargo-featalign
+use crate::prelude::*;
 
-pub trait GetById<'a> {
-	type Id: ?Sized;
-	type Item: ?Sized;
-
-	fn get_by_id<'b>(self, id: &'b Self::Id) -> Option<&'a Self::Item>
-	where
-		'a: 'b;
-}
-impl<'a> GetById<'a> for &[(&'a str, &'a str)] {
-	type Id = str;
-	type Item = str;
-
-	fn get_by_id<'b>(self, id: &'b Self::Id) -> Option<&'a Self::Item>
-	where
-		'a: 'b,
-	{
-		self.iter().find(|(k, _)| *k == id).map(|(_, v)| *v)
+// Match the string with a pattern which supports the `*` wildcard.
+pub fn is_glob_match(pattern: &str, s: &str) -> bool {
+	match pattern.split_once('*') {
+		Some((prefix, pattern)) => s.strip_prefix(prefix).is_some_and(|s| {
+			s.char_indices()
+				.map(|(i, _)| i)
+				.chain([s.len()])
+				.any(|i| is_glob_match(pattern, &s[i..]))
+		}),
+		None => pattern == s,
 	}
 }
-impl<'a> GetById<'a> for &'a Metadata {
-	type Id = PackageId;
-	type Item = Package;
 
-	fn get_by_id<'b>(self, id: &'b Self::Id) -> Option<&'a Self::Item>
-	where
-		'a: 'b,
-	{
-		self.packages.get_by_id(id)
-	}
+pub fn manifest_path_of(path: &Path) -> Cow<'_, Path> {
+	if path.is_file() { Cow::Borrowed(path) } else { Cow::Owned(path.join("Cargo.toml")) }
 }
-impl<'a> GetById<'a> for &'a [Package] {
-	type Id = PackageId;
-	type Item = Package;
 
-	fn get_by_id<'b>(self, id: &'b Self::Id) -> Option<&'a Self::Item>
-	where
-		'a: 'b,
-	{
-		self.iter().find(|p| &p.id == id)
-	}
-}
-impl<'a> GetById<'a> for &'a Resolve {
-	type Id = PackageId;
-	type Item = Node;
+// Collect the root package's and the workspace members' manifests, without `cargo metadata`.
+//
+// Each component of a member supports the `*` wildcard, the excluded directories are skipped.
+pub fn member_manifest_paths_of(manifest_path: &Path) -> Result<Vec<PathBuf>> {
+	let d = fs::read_to_string(manifest_path)?.parse::<DocumentMut>()?;
+	let root = manifest_path.parent().unwrap_or(Path::new("."));
+	let mut ps = Vec::new();
 
-	fn get_by_id<'b>(self, id: &'b Self::Id) -> Option<&'a Self::Item>
-	where
-		'a: 'b,
-	{
-		self.nodes.get_by_id(id)
+	if d.contains_key("package") {
+		ps.push(manifest_path.to_owned());
 	}
-}
-impl<'a> GetById<'a> for &'a [Node] {
-	type Id = PackageId;
-	type Item = Node;
 
-	fn get_by_id<'b>(self, id: &'b Self::Id) -> Option<&'a Self::Item>
-	where
-		'a: 'b,
-	{
-		self.iter().find(|n| &n.id == id)
-	}
-}
+	let Some(w) = d.get("workspace") else {
+		return Ok(ps);
+	};
+	let paths_of = |k| {
+		w.get(k)
+			.and_then(Item::as_array)
+			.into_iter()
+			.flatten()
+			.filter_map(|v| v.as_str())
+			.map(Path::new)
+			.collect::<Vec<_>>()
+	};
+	let excludes = paths_of("exclude").into_iter().map(|e| root.join(e)).collect::<Vec<_>>();
 
-pub fn manifest_path_of(path: &PathBuf) -> Cow<'_, Path> {
-	if path.is_file() {
-		Cow::Borrowed(path)
-	} else {
-		let mut p = path.to_owned();
+	for m in paths_of("members") {
+		let mut ds = vec![root.to_owned()];
 
-		p.push("Cargo.toml");
+		for c in m.components() {
+			let c = c.as_os_str().to_string_lossy();
 
-		Cow::Owned(p)
+			ds = if c.contains('*') {
+				ds.iter()
+					.filter_map(|d| fs::read_dir(d).ok())
+					.flatten()
+					.flatten()
+					.filter(|e| {
+						e.path().is_dir() && is_glob_match(&c, &e.file_name().to_string_lossy())
+					})
+					.map(|e| e.path())
+					.collect()
+			} else {
+				ds.into_iter().map(|d| d.join(&*c)).collect()
+			};
+		}
+
+		ps.extend(
+			ds.into_iter()
+				.filter(|d| !excludes.iter().any(|e| d.starts_with(e)))
+				.map(|d| d.join("Cargo.toml"))
+				.filter(|p| p.is_file()),
+		);
 	}
+
+	ps.sort();
+	ps.dedup();
+
+	Ok(ps)
 }
 
 pub fn diff(a: &str, b: &str) -> String {